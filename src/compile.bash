@@ -0,0 +1,954 @@
+# The parsing engine, driven by the tables above. It mirrors the behaviour of
+# clap as configured by ramen, and prints the same script as ramen does.
+set +e +u
+set +o pipefail 2>/dev/null
+IFS=$' \t\n'
+
+__r_argv=("$@")
+__r_argc=$#
+__r_i=0
+__r_cur=0
+__r_chain=(0)
+__r_posi=0
+__r_dashdash=0
+__r_pt=0
+__r_pend=-1
+__r_pvals=()
+__r_state=''
+__r_ptlvl=-1
+__r_ptvals=()
+__r_vals=()
+__r_owner=()
+__r_n=()
+__r_src=()
+__r_order=''
+# The smallest magnitude which overflows f64, 2^1024 - 2^970.
+__r_fmax=179769313486231580793728971405303415079934132710037826936173778980444968292764750946649017977587207096330286416692887910946555547851940402630657488671505820681908902000708383676273854845817711531764475730270069855571366959622842914819860834936475292719074168444365510704342711559699508093042880177904174497792
+for ((__r_g = 0; __r_g < ${#__r_akind[@]}; __r_g++)); do
+  __r_n[__r_g]=0
+  __r_src[__r_g]=''
+done
+
+# Quote a word for the shell, ex. it's => 'it'\''s'.
+__r_q() {
+  local s=$1 out='' q="'"
+  while [[ $s == *$q* ]]; do
+    out="$out${s%%$q*}$q\\$q$q"
+    s=${s#*$q}
+  done
+  __r_word="$q$out$s$q"
+}
+
+# Print a script which prints the message and exits, like clap errors.
+__r_exit() {
+  __r_q "$1"
+  if [ "$2" = 0 ]; then
+    printf "printf '%%s' %s\nexit 0\n\n" "$__r_word"
+  else
+    printf "printf '%%s' >&2 %s\nexit %s\n\n" "$__r_word" "$2"
+  fi
+  exit 0
+}
+
+# Fail with the message, followed by the usage if $2 is 1, or the usage
+# with the arguments in __r_used if $2 is 2.
+__r_fail() {
+  local message="error: $1"$'\n\n'
+  if [ "$2" = 2 ] && [ -n "$__r_used" ]; then
+    __r_smart_usage "$__r_used"
+    message="${message}Usage: $__r_usage"$'\n\n'
+  elif [ "$2" != 0 ]; then
+    message="${message}Usage: ${__r_lusage[__r_cur]}"$'\n\n'
+  fi
+  __r_exit "${message}For more information, try '--help'."$'\n' 2
+}
+
+# The usage with the given arguments, along with the required ones, in
+# __r_usage, ex. `upload --threads <threads> <SRC> [DST]`.
+__r_smart_usage() {
+  local req='' seen=' ' groups='' members=' ' out g k
+  for g in ${__r_largs[__r_cur]}; do
+    [ "${__r_areq[g]}" = 1 ] && req="$req ${__r_areqs[g]} $g"
+  done
+  for ((k = 0; k < ${#__r_rgl[@]}; k++)); do
+    [ "${__r_rgl[k]}" = "$__r_cur" ] || continue
+    groups="$groups${__r_rgd[k]} "
+    members="$members${__r_rgm[k]} "
+  done
+  out="${__r_lusename[__r_cur]} "
+  for g in $req $1; do
+    [[ $seen == *" $g "* || $members == *" $g "* ]] && continue
+    seen="$seen$g "
+    if [ "$g" = help ]; then
+      out="$out--help "
+      continue
+    fi
+    [ "${__r_apos[g]}" = 1 ] || out="$out${__r_adisp[g]} "
+  done
+  out="$out$groups"
+  for g in ${__r_lpos[__r_cur]}; do
+    [[ $members == *" $g "* ]] && continue
+    if [[ $seen == *" $g "* ]]; then
+      out="$out${__r_areqdisp[g]} "
+    else
+      out="$out${__r_aoptdisp[g]} "
+    fi
+  done
+  case ${__r_lpass[__r_cur]} in
+    trailing) out="$out[-- [ARGS]...] " ;;
+    unknown) out="$out[ARGS]... " ;;
+  esac
+  [ -n "${__r_lsubs[__r_cur]}" ] && out="$out<COMMAND> "
+  __r_usage=${out% }
+}
+
+# The arguments of the level given so far, in __r_used.
+__r_given() {
+  local g
+  __r_used=''
+  for g in $__r_order; do
+    [ "${__r_alvl[g]}" = "$__r_cur" ] && __r_used="$__r_used $g"
+  done
+}
+
+__r_show_help() {
+  __r_help_text "$1"
+  __r_exit "$__r_text" 0
+}
+
+# The current positional argument of the level, in __r_g.
+__r_slot() {
+  local slots
+  slots=(${__r_lpos[__r_cur]})
+  [ "$__r_posi" -lt "${#slots[@]}" ] || return 1
+  __r_g=${slots[__r_posi]}
+}
+
+# Find the argument of the level by its long (or short) name, in __r_g.
+__r_find() {
+  local g
+  for g in ${__r_largs[__r_cur]}; do
+    if [ "$1" = long ]; then
+      [ "${__r_along[g]}" = "$2" ] || continue
+    elif [ "${__r_ashort[g]}" != "$2" ]; then
+      continue
+    fi
+    __r_g=$g
+    return 0
+  done
+  return 1
+}
+
+__r_is_negative_number() {
+  local re='^-[0-9]+(\.[0-9]*)?([eE][0-9]+)?$'
+  [[ $1 =~ $re ]]
+}
+
+# Fail with an unexpected argument, $2 is long or short for options.
+__r_unexpected() {
+  local message="unexpected argument '$1' found" usage=1 tips='' trailing=0 g longs=()
+  __r_sub_tip=''
+  __r_resolve try
+  if [ -n "${__r_lpos[__r_cur]}" ] || [ "${__r_lpass[__r_cur]}" != none ]; then
+    [ -n "$2" ] && [ "$__r_dashdash" = 0 ] && trailing=1
+  fi
+  if [ "$2" = long ]; then
+    __r_given
+    usage=2
+    for g in ${__r_largs[__r_cur]}; do
+      [ -n "${__r_along[g]}" ] && longs[${#longs[@]}]=${__r_along[g]}
+    done
+    __r_suggest "${1#--}" "${longs[@]}" help
+    if [ ${#__r_similar[@]} -gt 0 ]; then
+      g=${__r_similar[${#__r_similar[@]} - 1]}
+      tips=$'\n'"  tip: a similar argument exists: '--$g'"
+      # The suggested argument is shown in the usage.
+      if __r_find long "$g"; then
+        __r_used="$__r_used $__r_g"
+      else
+        __r_used="$__r_used help"
+      fi
+    else
+      __r_suggest_sub "${1#--}"
+    fi
+    [ -n "$tips$__r_sub_tip" ] && [ "${__r_lpass[__r_cur]}" = none ] && trailing=0
+  fi
+  [ "$trailing" = 1 ] && tips="$tips"$'\n'"  tip: to pass '$1' as a value, use '-- $1'"
+  [ -n "$__r_sub_tip" ] && tips="$tips"$'\n'"  tip: $__r_sub_tip"
+  [ -n "$tips" ] && message="$message"$'\n'"$tips"
+  __r_fail "$message" "$usage"
+}
+
+# The option similar to $1 of the subcommand given first in the rest of
+# the arguments, in __r_sub_tip, ex. `'apply --force' exists`.
+__r_suggest_sub() {
+  local l g k at=-1 longs
+  __r_sub_tip=''
+  for l in ${__r_lsubs[__r_cur]}; do
+    longs=()
+    for g in ${__r_largs[l]}; do
+      [ -n "${__r_along[g]}" ] && longs[${#longs[@]}]=${__r_along[g]}
+    done
+    __r_suggest "$1" "${longs[@]}" help
+    [ ${#__r_similar[@]} -gt 0 ] || continue
+    for ((k = __r_i; k < __r_argc; k++)); do
+      [ "${__r_argv[k]}" = "${__r_lname[l]}" ] || continue
+      if [ "$at" = -1 ] || [ "$k" -lt "$at" ]; then
+        at=$k
+        __r_sub_tip="'${__r_lname[l]} --${__r_similar[${#__r_similar[@]} - 1]}' exists"
+      fi
+      break
+    done
+  done
+}
+
+# The Jaro similarity of two words, scaled by a million in __r_score, and
+# __r_close is 1 if it's above 0.7.
+__r_jaro() {
+  local a=$1 b=$2 la=${#1} lb=${#2} range i j hi m=0 t=0 k=0 af=() bf=()
+  __r_score=0
+  __r_close=0
+  [ "$la" -gt 0 ] && [ "$lb" -gt 0 ] || return
+  range=$(((la > lb ? la : lb) / 2 - 1))
+  [ "$range" -ge 0 ] || range=0
+  for ((i = 0; i < la; i++)); do
+    hi=$((i + range + 1 < lb ? i + range + 1 : lb))
+    for ((j = (i > range ? i - range : 0); j < hi; j++)); do
+      if [ -z "${bf[j]}" ] && [ "${a:i:1}" = "${b:j:1}" ]; then
+        af[i]=1
+        bf[j]=1
+        m=$((m + 1))
+        break
+      fi
+    done
+  done
+  [ "$m" -gt 0 ] || return
+  for ((i = 0; i < la; i++)); do
+    [ -n "${af[i]}" ] || continue
+    while [ -z "${bf[k]}" ]; do k=$((k + 1)); done
+    [ "${a:i:1}" = "${b:k:1}" ] || t=$((t + 1))
+    k=$((k + 1))
+  done
+  t=$((t / 2))
+  __r_score=$(((m * 1000000 / la + m * 1000000 / lb + (m - t) * 1000000 / m) / 3))
+  if [ $((10 * (m * m * lb + m * m * la + (m - t) * la * lb))) -gt $((21 * la * lb * m)) ]; then
+    __r_close=1
+  fi
+}
+
+# The candidates similar to the word $1, from the least similar one, in
+# __r_similar, the same as clap suggests them.
+__r_suggest() {
+  local word=$1 scores=() words=() c k n
+  shift
+  for c in "$@"; do
+    __r_jaro "$word" "$c"
+    [ "$__r_close" = 1 ] || continue
+    n=${#words[@]}
+    for ((k = n; k > 0; k--)); do
+      [ "${scores[k - 1]}" -gt "$__r_score" ] || break
+      scores[k]=${scores[k - 1]}
+      words[k]=${words[k - 1]}
+    done
+    scores[k]=$__r_score
+    words[k]=$c
+  done
+  __r_similar=("${words[@]}")
+}
+
+# Normalize a decimal number into __r_sign, __r_int and __r_frac. The
+# exponent is expected to be bounded, see __r_magnitude.
+__r_norm() {
+  local x=$1 exp=0 int frac pad
+  __r_sign=''
+  case $x in
+    -*) __r_sign=-; x=${x#-} ;;
+    +*) x=${x#+} ;;
+  esac
+  case $x in
+    *[eE]*) exp=${x#*[eE]}; x=${x%%[eE]*} ;;
+  esac
+  exp=${exp#+}
+  case $x in
+    *.*) int=${x%%.*}; frac=${x#*.} ;;
+    *) int=$x; frac='' ;;
+  esac
+  case $exp in -*) exp=$((-10#${exp#-})) ;; *) exp=$((10#$exp)) ;; esac
+  if [ "$exp" -gt 0 ]; then
+    if [ ${#frac} -lt "$exp" ]; then
+      printf -v pad '%*s' $((exp - ${#frac})) ''
+      frac=$frac${pad// /0}
+    fi
+    int=$int${frac:0:exp}
+    frac=${frac:exp}
+  elif [ "$exp" -lt 0 ]; then
+    exp=$((-exp))
+    if [ ${#int} -lt "$exp" ]; then
+      printf -v pad '%*s' $((exp - ${#int})) ''
+      int=${pad// /0}$int
+    fi
+    frac=${int:${#int}-exp}$frac
+    int=${int:0:${#int}-exp}
+  fi
+  int=${int#"${int%%[!0]*}"}
+  frac=${frac%"${frac##*[!0]}"}
+  [ -n "$int$frac" ] || __r_sign=''
+  __r_int=$int
+  __r_frac=$frac
+}
+
+# The magnitude of a decimal number, __r_e such that the number is
+# 0.D x 10^__r_e with a non-zero first digit of D, empty for zero. Huge
+# exponents are capped, so that the arithmetic doesn't overflow.
+__r_magnitude() {
+  local x=${1#[+-]} exp='' int frac digits zeros sign=+
+  case $x in
+    *[eE]*) exp=${x#*[eE]}; x=${x%%[eE]*} ;;
+  esac
+  case $x in
+    *.*) int=${x%%.*}; frac=${x#*.} ;;
+    *) int=$x; frac='' ;;
+  esac
+  digits=$int$frac
+  zeros=${digits%%[!0]*}
+  __r_e=''
+  [ ${#zeros} -lt ${#digits} ] || return 0
+  case $exp in
+    -*) sign=-; exp=${exp#-} ;;
+    +*) exp=${exp#+} ;;
+  esac
+  exp=${exp#"${exp%%[!0]*}"}
+  [ ${#exp} -le 7 ] || exp=10000000
+  __r_e=$((${#int} - ${#zeros} $sign ${exp:-0}))
+}
+
+# Compare two decimal numbers, __r_c is -1, 0 or 1.
+__r_cmp() {
+  local s1 i1 f1 s2 i2 f2
+  __r_norm "$1"; s1=$__r_sign; i1=$__r_int; f1=$__r_frac
+  __r_norm "$2"; s2=$__r_sign; i2=$__r_int; f2=$__r_frac
+  if [ "$s1" != "$s2" ]; then
+    if [ "$s1" = - ]; then __r_c=-1; else __r_c=1; fi
+    return
+  fi
+  __r_c=0
+  if [ ${#i1} -ne ${#i2} ]; then
+    if [ ${#i1} -lt ${#i2} ]; then __r_c=-1; else __r_c=1; fi
+  else
+    while [ ${#f1} -lt ${#f2} ]; do f1="${f1}0"; done
+    while [ ${#f2} -lt ${#f1} ]; do f2="${f2}0"; done
+    if [[ "$i1$f1" < "$i2$f2" ]]; then
+      __r_c=-1
+    elif [[ "$i1$f1" > "$i2$f2" ]]; then
+      __r_c=1
+    fi
+  fi
+  if [ "$s1" = - ]; then __r_c=$((-__r_c)); fi
+}
+
+# Validate a value of the argument $1, the same as the value parser.
+__r_check() {
+  local g=$1 v=$2 k found=0 re invalid
+  for ((k = 0; k < ${#__r_cho[@]}; k++)); do
+    [ "${__r_cho[k]}" = "$g" ] || continue
+    found=1
+    [ "${__r_chv[k]}" = "$v" ] && return 0
+  done
+  if [ "$found" = 1 ]; then
+    local message="invalid value '$v' for '${__r_adisp[g]}'"$'\n'"  [possible values: ${__r_apossible[g]}]"
+    local choices=()
+    for ((k = 0; k < ${#__r_cho[@]}; k++)); do
+      [ "${__r_cho[k]}" = "$g" ] && choices[${#choices[@]}]=${__r_chv[k]}
+    done
+    __r_suggest "$v" "${choices[@]}"
+    if [ ${#__r_similar[@]} -gt 0 ]; then
+      message="$message"$'\n\n'"  tip: a similar value exists: '${__r_similar[${#__r_similar[@]} - 1]}'"
+    fi
+    __r_fail "$message" 0
+  fi
+  case ${__r_atype[g]} in
+    integer) re='^[+-]?[0-9]+$' ;;
+    number | float) re='^[+-]?([0-9]+\.?[0-9]*|\.[0-9]+)([eE][+-]?[0-9]+)?$' ;;
+    *) return 0 ;;
+  esac
+  # The integers are in the range of i64, and the other numbers are finite
+  # as f64, where the ones too small to tell apart from zero are zero.
+  local n=$v digits=${v#[+-]} limit=9223372036854775807 valid=1
+  if ! [[ $v =~ $re ]]; then
+    valid=''
+  elif [ "${__r_atype[g]}" = integer ]; then
+    digits=${digits#"${digits%%[!0]*}"}
+    [ "${v:0:1}" = - ] && limit=9223372036854775808
+    if [ ${#digits} -gt 19 ] || { [ ${#digits} = 19 ] && [[ $digits > $limit ]]; }; then
+      valid=''
+    fi
+  else
+    __r_magnitude "$v"
+    if [ -z "$__r_e" ] || [ "$__r_e" -lt -323 ]; then
+      n=0
+    elif [ "$__r_e" -gt 309 ]; then
+      valid=''
+    elif [ "$__r_e" = 309 ]; then
+      __r_cmp "$digits" "$__r_fmax"
+      [ "$__r_c" -lt 0 ] || valid=''
+    fi
+  fi
+  [ -n "$valid" ] || __r_fail "invalid value '$v' for '${__r_adisp[g]}': \"$v\" is not a valid ${__r_atype[g]}" 0
+  local min=${__r_amin[g]} max=${__r_amax[g]}
+  if [ -n "$min" ]; then
+    __r_cmp "$n" "$min"
+    [ "$__r_c" -lt 0 ] && invalid=1
+  fi
+  if [ -n "$max" ]; then
+    __r_cmp "$n" "$max"
+    [ "$__r_c" -gt 0 ] && invalid=1
+  fi
+  [ -n "$invalid" ] || return 0
+  if [ -n "$min" ] && [ -n "$max" ]; then
+    __r_fail "invalid value '$v' for '${__r_adisp[g]}': $v is not in $min..=$max" 0
+  elif [ -n "$min" ]; then
+    __r_fail "invalid value '$v' for '${__r_adisp[g]}': $v is less than $min" 0
+  else
+    __r_fail "invalid value '$v' for '${__r_adisp[g]}': $v is greater than $max" 0
+  fi
+}
+
+# Validate the number of occurrences of a counting flag, which is given by
+# an environment variable.
+__r_check_count() {
+  local re='^[+-]?[0-9]+$' digits=${2#[+-]} reason
+  if [ -z "$2" ]; then
+    reason='cannot parse integer from empty string'
+  elif ! [[ $2 =~ $re ]]; then
+    reason='invalid digit found in string'
+  else
+    while [ "${digits:0:1}" = 0 ] && [ ${#digits} -gt 1 ]; do digits=${digits#0}; done
+    if [ ${#digits} -gt 18 ]; then
+      reason='number too large to fit in target type'
+    elif [ "${2:0:1}" = - ] && [ "$digits" != 0 ]; then
+      reason="-$digits is not in 0..=255"
+    elif [ "$digits" -gt 255 ]; then
+      reason="$digits is not in 0..=255"
+    fi
+  fi
+  [ -z "$reason" ] || __r_fail "invalid value '$2' for '${__r_adisp[$1]}': $reason" 0
+}
+
+__r_add() {
+  __r_check "$1" "$2"
+  __r_vals[${#__r_vals[@]}]=$2
+  __r_owner[${#__r_owner[@]}]=$1
+  __r_n[$1]=$((__r_n[$1] + 1))
+  __r_explicit "$1" cli
+}
+
+# Mark the argument as given, in the order of the command line.
+__r_explicit() {
+  if [ -z "${__r_src[$1]}" ]; then
+    __r_src[$1]=$2
+    __r_order="$__r_order $1"
+  fi
+}
+
+# Fail with the missing value of the argument $1.
+__r_no_value() {
+  local message="a value is required for '${__r_adisp[$1]}' but none was supplied"
+  if [ -n "${__r_apossible[$1]}" ]; then
+    message="$message"$'\n'"  [possible values: ${__r_apossible[$1]}]"
+  fi
+  __r_fail "$message" 0
+}
+
+# Take the values given to the argument $__r_pend so far, which are
+# validated when the next argument is met, like clap does. If $1 is try,
+# as another error is being reported, the invalid values are left out
+# instead, but the argument still counts as given in the usage.
+__r_resolve() {
+  local g=$__r_pend v
+  [ "$g" != -1 ] || return 0
+  __r_pend=-1
+  if [ ${#__r_pvals[@]} = 0 ]; then
+    [ "$1" = try ] && return
+    __r_no_value "$g"
+  fi
+  if [ "${__r_amulti[g]}" = 0 ] && [ "${__r_n[g]}" -gt 0 ]; then
+    if [ "$1" = try ]; then
+      __r_order=" $__r_order "
+      __r_order=${__r_order/ $g / }
+      __r_src[g]=''
+      return
+    fi
+    __r_fail "the argument '${__r_adisp[g]}' cannot be used multiple times" 1
+  fi
+  for v in "${__r_pvals[@]}"; do
+    if [ "$1" = try ]; then
+      __r_explicit "$g" cli
+      [ -z "$(__r_check "$g" "$v")" ] || return
+    fi
+    __r_add "$g" "$v"
+  done
+}
+
+# Handle the option $__r_g, with the value after `=` if $1 is 1.
+__r_take() {
+  local g=$__r_g
+  __r_state=''
+  case ${__r_akind[g]} in
+    flag | count)
+      if [ "$1" = 1 ]; then
+        __r_given
+        __r_used="$__r_used $g"
+        __r_fail "unexpected value '$2' for '${__r_adisp[g]}' found; no more were expected" 2
+      fi
+      __r_resolve
+      if [ "${__r_akind[g]}" = flag ] && [ "${__r_n[g]}" -gt 0 ]; then
+        __r_fail "the argument '${__r_adisp[g]}' cannot be used multiple times" 1
+      fi
+      __r_n[g]=$((__r_n[g] + 1))
+      # A counting flag moves to the last one given, like clap does.
+      __r_order=" $__r_order "
+      __r_order=${__r_order/ $g / }
+      __r_src[g]=''
+      __r_explicit "$g" cli
+      return
+      ;;
+  esac
+  __r_resolve
+  __r_pend=$g
+  __r_pvals=()
+  if [ "$1" = 1 ]; then
+    __r_pvals=("$2")
+    __r_resolve
+  else
+    __r_state=opt
+  fi
+}
+
+# Whether the token starting with a dash is a value rather than an option,
+# ex. a negative number, or an unknown option passed through.
+__r_hyphen_value() {
+  local g flags c
+  case $__r_tok in
+    --*)
+      c=${__r_tok#--}
+      c=${c%%=*}
+      [ "$c" != help ] && ! __r_find long "$c" && [ "${__r_lpass[__r_cur]}" = unknown ] && ! __r_slot
+      return
+      ;;
+  esac
+  if [ "$__r_state" = opt ]; then
+    g=$__r_pend
+  elif __r_slot; then
+    g=$__r_g
+  else
+    g=-1
+  fi
+  if [ "$g" != -1 ] && [ -n "${__r_atype[g]}" ] && __r_is_negative_number "$__r_tok"; then
+    return 0
+  fi
+  [ "${__r_lpass[__r_cur]}" = unknown ] && ! __r_slot || return 1
+  flags=${__r_tok#-}
+  while [ -n "$flags" ]; do
+    c=${flags:0:1}
+    flags=${flags:1}
+    [ "$c" = h ] || __r_find short "$c" || return 0
+  done
+  return 1
+}
+
+__r_passthrough() {
+  __r_resolve
+  __r_pt=1
+  __r_ptlvl=$__r_cur
+  __r_ptvals=("$__r_tok")
+}
+
+__r_long() {
+  local name=${__r_tok#--} value='' has=0
+  case $name in
+    *=*) value=${name#*=}; name=${name%%=*}; has=1 ;;
+  esac
+  if [ "$name" = help ]; then
+    __r_resolve
+    __r_show_help "$__r_cur"
+  fi
+  __r_find long "$name" || __r_unexpected "--$name" long
+  __r_take "$has" "$value"
+}
+
+__r_short() {
+  local flags=${__r_tok#-} c
+  while [ -n "$flags" ]; do
+    c=${flags:0:1}
+    flags=${flags:1}
+    if [ "$c" = h ]; then
+      __r_resolve
+      __r_show_help "$__r_cur"
+    fi
+    __r_find short "$c" || __r_unexpected "-$c" short
+    case ${__r_akind[__r_g]} in
+      flag | count) __r_take 0 '' ;;
+      *)
+        case $flags in
+          =*) __r_take 1 "${flags#=}" ;;
+          ?*) __r_take 1 "$flags" ;;
+          *) __r_take 0 '' ;;
+        esac
+        return
+        ;;
+    esac
+  done
+}
+
+# Find the subcommand of the level by its name or alias, and enter it
+# unless $2 is given.
+__r_find_sub() {
+  local l name
+  for l in ${__r_lsubs[__r_cur]}; do
+    for name in ${__r_lnames[l]}; do
+      [ "$name" = "$1" ] || continue
+      [ -n "$2" ] && return 0
+      # The value left is validated after the subcommand, like clap does.
+      __r_lpend[__r_cur]=$__r_pend
+      __r_lpval[__r_cur]=${__r_pvals[0]}
+      __r_pend=-1
+      __r_cur=$l
+      __r_chain[${#__r_chain[@]}]=$l
+      __r_posi=0
+      return 0
+    done
+  done
+  return 1
+}
+
+# The help subcommand, ex. `help apply`.
+__r_help_command() {
+  while [ "$__r_i" -lt "$__r_argc" ]; do
+    __r_tok=${__r_argv[__r_i]}
+    __r_i=$((__r_i + 1))
+    if [ "$__r_tok" = help ] && [ -n "${__r_lsubs[__r_cur]}" ]; then
+      __r_help_help
+    fi
+    __r_find_sub "$__r_tok" || __r_fail "unrecognized subcommand '$__r_tok'" 1
+  done
+  __r_show_help "$__r_cur"
+}
+
+# The help of the help subcommand, ex. `help help apply`.
+__r_help_help() {
+  local key=$__r_cur k found message
+  for ((k = 0; k < ${#__r_hkey[@]}; k++)); do
+    [ "${__r_hkey[k]}" = "$key" ] && found=$k
+  done
+  while [ "$__r_i" -lt "$__r_argc" ]; do
+    __r_tok=${__r_argv[__r_i]}
+    __r_i=$((__r_i + 1))
+    message=${__r_herr[found]}
+    key="$key $__r_tok"
+    found=''
+    for ((k = 0; k < ${#__r_hkey[@]}; k++)); do
+      [ "${__r_hkey[k]}" = "$key" ] && found=$k
+    done
+    [ -n "$found" ] || __r_exit "${message%%__RAMEN_SUBCOMMAND__*}$__r_tok${message#*__RAMEN_SUBCOMMAND__}" 2
+  done
+  __r_exit "${__r_htext[found]}" 0
+}
+
+__r_positional() {
+  if __r_slot; then
+    if [ "$__r_pend" != "$__r_g" ] || [ "${__r_amulti[__r_g]}" = 0 ]; then
+      __r_resolve
+      __r_pend=$__r_g
+      __r_pvals=()
+    fi
+    __r_pvals[${#__r_pvals[@]}]=$__r_tok
+    __r_state=''
+    if [ "${__r_amulti[__r_g]}" = 1 ]; then
+      __r_state=pos
+    else
+      __r_posi=$((__r_posi + 1))
+    fi
+  elif [ "${__r_lpass[__r_cur]}" = unknown ]; then
+    __r_passthrough
+  elif [ "$__r_dashdash" = 1 ] && [ -n "${__r_lsubs[__r_cur]}" ] &&
+    { [ "$__r_tok" = help ] || __r_find_sub "$__r_tok" test; }; then
+    __r_fail "unexpected argument '$__r_tok' found"$'\n\n'"  tip: subcommand '$__r_tok' exists; to use it, remove the '--' before it" 1
+  elif [ -n "${__r_lsubs[__r_cur]}" ]; then
+    local names=()
+    names=(${__r_lsubnames[__r_cur]//,/})
+    __r_suggest "$__r_tok" "${names[@]}"
+    if [ ${#__r_similar[@]} = 1 ]; then
+      __r_fail "unrecognized subcommand '$__r_tok'"$'\n\n'"  tip: a similar subcommand exists: '${__r_similar[0]}'" 1
+    elif [ ${#__r_similar[@]} -gt 1 ]; then
+      local similar
+      similar=$(printf "'%s', " "${__r_similar[@]}")
+      __r_fail "unrecognized subcommand '$__r_tok'"$'\n\n'"  tip: some similar subcommands exist: ${similar%, }" 1
+    fi
+    [ -z "${__r_lpos[__r_cur]}" ] && [ "${__r_lpass[__r_cur]}" = none ] && __r_fail "unrecognized subcommand '$__r_tok'" 1
+    __r_unexpected "$__r_tok"
+  else
+    __r_unexpected "$__r_tok"
+  fi
+}
+
+while [ "$__r_i" -lt "$__r_argc" ]; do
+  __r_tok=${__r_argv[__r_i]}
+  __r_i=$((__r_i + 1))
+  if [ "$__r_pt" = 1 ]; then
+    __r_ptvals[${#__r_ptvals[@]}]=$__r_tok
+    continue
+  fi
+  if [ "$__r_dashdash" = 0 ]; then
+    case $__r_tok in
+      --)
+        __r_dashdash=1
+        __r_state=''
+        if [ "${__r_lpass[__r_cur]}" = trailing ]; then
+          __r_pt=1
+          __r_ptlvl=$__r_cur
+        fi
+        continue
+        ;;
+      --*)
+        if ! __r_hyphen_value; then
+          __r_long
+          continue
+        fi
+        ;;
+      -?*)
+        if ! __r_hyphen_value; then
+          __r_short
+          continue
+        fi
+        ;;
+    esac
+    if [ "$__r_state" = opt ]; then
+      __r_pvals=("$__r_tok")
+      __r_state=''
+      continue
+    fi
+    if [ "$__r_state" != pos ]; then
+      __r_find_sub "$__r_tok" && continue
+      if [ "$__r_tok" = help ] && [ -n "${__r_lsubs[__r_cur]}" ]; then
+        __r_help_command
+      fi
+    fi
+  fi
+  __r_positional
+done
+__r_resolve
+
+# Take the values of the absent arguments from the environment variables
+# or the defaults, then validate the levels from the deepest one up.
+for ((__r_k = ${#__r_chain[@]} - 1; __r_k >= 0; __r_k--)); do
+  __r_cur=${__r_chain[__r_k]}
+  if [ "$__r_k" -lt $((${#__r_chain[@]} - 1)) ]; then
+    __r_pend=${__r_lpend[__r_cur]}
+    __r_pvals=("${__r_lpval[__r_cur]}")
+    __r_resolve
+  fi
+  for __r_g in ${__r_largs[__r_cur]}; do
+    [ -z "${__r_src[__r_g]}" ] || continue
+    __r_env=${__r_aenv[__r_g]}
+    if [ -n "$__r_env" ] && [ -n "${!__r_env+x}" ]; then
+      __r_value=${!__r_env}
+      case ${__r_akind[__r_g]} in
+        flag)
+          case $__r_value in
+            true | false) ;;
+            '') __r_fail "a value is required for '${__r_adisp[__r_g]}' but none was supplied"$'\n'"  [possible values: true, false]" 0 ;;
+            *) __r_fail "invalid value '$__r_value' for '${__r_adisp[__r_g]}'"$'\n'"  [possible values: true, false]" 0 ;;
+          esac
+          ;;
+        count) __r_check_count "$__r_g" "$__r_value" ;;
+        *) __r_check "$__r_g" "$__r_value" ;;
+      esac
+      __r_vals[${#__r_vals[@]}]=$__r_value
+      __r_owner[${#__r_owner[@]}]=$__r_g
+      __r_explicit "$__r_g" env
+      continue
+    fi
+    for ((__r_d = 0; __r_d < ${#__r_dfo[@]}; __r_d++)); do
+      [ "${__r_dfo[__r_d]}" = "$__r_g" ] || continue
+      __r_check "$__r_g" "${__r_dfv[__r_d]}"
+      __r_vals[${#__r_vals[@]}]=${__r_dfv[__r_d]}
+      __r_owner[${#__r_owner[@]}]=$__r_g
+    done
+  done
+
+  if [ "$__r_k" = $((${#__r_chain[@]} - 1)) ] && [ -n "${__r_lsubs[__r_cur]}" ]; then
+    __r_fail "'${__r_lbin[__r_cur]}' requires a subcommand but one was not provided"$'\n'"  [subcommands: ${__r_lsubnames[__r_cur]}]" 1
+  fi
+
+  # The arguments required by the given ones come first in the usage.
+  __r_given
+  __r_reqby=''
+  for __r_g in $__r_used; do
+    __r_reqby="$__r_reqby ${__r_areqs[__r_g]}"
+  done
+  for __r_g in $__r_used; do
+    __r_with=()
+    __r_others=' '
+    for __r_h in ${__r_aconf[__r_g]}; do
+      if [ -n "${__r_src[__r_h]}" ]; then
+        __r_with[${#__r_with[@]}]=${__r_adisp[__r_h]}
+        __r_others="$__r_others$__r_h "
+      fi
+    done
+    [ ${#__r_with[@]} = 0 ] && continue
+    if [ ${#__r_with[@]} = 1 ]; then
+      __r_message="the argument '${__r_adisp[__r_g]}' cannot be used with '${__r_with[0]}'"
+    else
+      __r_message="the argument '${__r_adisp[__r_g]}' cannot be used with:"
+      for __r_h in "${__r_with[@]}"; do
+        __r_message="$__r_message"$'\n'"  $__r_h"
+      done
+    fi
+    # The usage shows the given arguments, except the conflicting ones,
+    # preceded by the ones they require.
+    __r_kept=''
+    for __r_h in $__r_used; do
+      [[ $__r_others == *" $__r_h "* ]] || __r_kept="$__r_kept $__r_h"
+    done
+    __r_used=''
+    for __r_h in $__r_kept; do
+      for __r_d in ${__r_areqs[__r_h]}; do
+        [[ " $__r_kept $__r_others " == *" $__r_d "* ]] || __r_used="$__r_used $__r_d"
+      done
+    done
+    __r_used="$__r_reqby $__r_used $__r_kept"
+    __r_fail "$__r_message" 2
+  done
+
+  # An argument is missing if it's required, or required by a given one,
+  # unless a conflicting one is given. A group is missing if none of its
+  # arguments is given.
+  __r_req=''
+  for __r_g in ${__r_largs[__r_cur]}; do
+    [ "${__r_areq[__r_g]}" = 1 ] && __r_req="$__r_req ${__r_areqs[__r_g]} $__r_g"
+  done
+  __r_req="$__r_req $__r_reqby"
+  __r_missing=''
+  for __r_g in $__r_req; do
+    [ -z "${__r_src[__r_g]}" ] || continue
+    __r_excused=0
+    for __r_h in ${__r_aconf[__r_g]}; do
+      [ -n "${__r_src[__r_h]}" ] && __r_excused=1
+    done
+    [ "$__r_excused" = 1 ] || __r_missing="$__r_missing $__r_g"
+  done
+  __r_groups=''
+  __r_members=' '
+  for ((__r_j = 0; __r_j < ${#__r_rgl[@]}; __r_j++)); do
+    [ "${__r_rgl[__r_j]}" = "$__r_cur" ] || continue
+    __r_found=0
+    for __r_h in ${__r_rgm[__r_j]}; do
+      [ -n "${__r_src[__r_h]}" ] && __r_found=1
+    done
+    if [ "$__r_found" = 0 ]; then
+      __r_groups="$__r_groups"$'\n'"  ${__r_rgd[__r_j]}"
+      __r_members="$__r_members${__r_rgm[__r_j]} "
+    fi
+  done
+  if [ -n "$__r_missing$__r_groups" ]; then
+    # Like clap, all of the absent required arguments are listed then.
+    __r_message=''
+    __r_seen=' '
+    for __r_g in $__r_req $__r_missing; do
+      [ -z "${__r_src[__r_g]}" ] || continue
+      [[ $__r_seen$__r_members == *" $__r_g "* ]] && continue
+      __r_seen="$__r_seen$__r_g "
+      [ "${__r_apos[__r_g]}" = 1 ] || __r_message="$__r_message"$'\n'"  ${__r_adisp[__r_g]}"
+    done
+    __r_message="$__r_message$__r_groups"
+    for __r_g in ${__r_lpos[__r_cur]}; do
+      [[ $__r_seen == *" $__r_g "* ]] && __r_message="$__r_message"$'\n'"  ${__r_areqdisp[__r_g]}"
+    done
+    __r_used="$__r_reqby $__r_used $__r_missing"
+    __r_fail "the following required arguments were not provided:$__r_message" 2
+  fi
+done
+
+# Compose the script, the same as ramen does.
+__r_out=''
+__r_emit() {
+  __r_out="$__r_out$1"$'\n'
+}
+for __r_l in "${__r_chain[@]}"; do
+  for __r_g in ${__r_largs[__r_l]}; do
+    __r_var=${__r_avar[__r_g]}
+    __r_values=()
+    for ((__r_k = 0; __r_k < ${#__r_owner[@]}; __r_k++)); do
+      [ "${__r_owner[__r_k]}" = "$__r_g" ] && __r_values[${#__r_values[@]}]=${__r_vals[__r_k]}
+    done
+    case ${__r_akind[__r_g]} in
+      flag)
+        __r_value=false
+        [ "${__r_n[__r_g]}" -gt 0 ] && __r_value=true
+        [ ${#__r_values[@]} -gt 0 ] && __r_value=${__r_values[0]}
+        __r_emit "$__r_kw_s$__r_var=$__r_value"
+        ;;
+      count)
+        __r_value=${__r_n[__r_g]}
+        [ ${#__r_values[@]} -gt 0 ] && __r_value=$((10#${__r_values[0]#[+-]}))
+        [ "$__r_value" -gt 255 ] && __r_value=255
+        __r_cap=${__r_acap[__r_g]}
+        [ -n "$__r_cap" ] && [ "$__r_value" -gt "$__r_cap" ] && __r_value=$__r_cap
+        __r_emit "$__r_kw_i$__r_var=$__r_value"
+        ;;
+      *)
+        if [ "${__r_amulti[__r_g]}" = 1 ]; then
+          __r_words=''
+          for ((__r_k = 0; __r_k < ${#__r_values[@]}; __r_k++)); do
+            __r_value=${__r_values[__r_k]}
+            if [ "${__r_aint[__r_g]}" = 1 ]; then
+              __r_norm "$__r_value"
+              __r_value=$__r_sign${__r_int:-0}
+            fi
+            __r_q "$__r_value"
+            if [ "$__r_indexed" = 1 ]; then
+              __r_emit "$__r_kw_s${__r_var}_$__r_k=$__r_word"
+            else
+              __r_words="$__r_words${__r_words:+ }$__r_word"
+            fi
+          done
+          [ "$__r_indexed" = 1 ] || __r_emit "$__r_kw_a$__r_var=($__r_words)"
+          __r_emit "$__r_kw_i${__r_var}_count=${#__r_values[@]}"
+        else
+          if [ "${__r_aint[__r_g]}" = 1 ] && [ -n "${__r_values[0]}" ]; then
+            # In plain decimal, declare -i reads 08 as octal.
+            __r_norm "${__r_values[0]}"
+            __r_q "$__r_sign${__r_int:-0}"
+            __r_emit "$__r_kw_i$__r_var=$__r_word"
+          else
+            __r_q "${__r_values[0]}"
+            __r_emit "$__r_kw_s$__r_var=$__r_word"
+          fi
+        fi
+        ;;
+    esac
+  done
+done
+if [ -n "$__r_cmdvar" ]; then
+  __r_path=''
+  for __r_l in "${__r_chain[@]}"; do
+    [ "$__r_l" = 0 ] || __r_path="$__r_path${__r_path:+ }${__r_lname[__r_l]}"
+  done
+  __r_q "$__r_path"
+  __r_emit "$__r_kw_s$__r_cmdvar=$__r_word"
+fi
+__r_ptout=-1
+for __r_l in "${__r_chain[@]}"; do
+  [ "${__r_lpass[__r_l]}" = none ] || __r_ptout=$__r_l
+done
+if [ "$__r_ptout" != -1 ]; then
+  __r_words='set --'
+  if [ "$__r_ptout" = "$__r_ptlvl" ]; then
+    for __r_value in "${__r_ptvals[@]}"; do
+      __r_q "$__r_value"
+      __r_words="$__r_words $__r_word"
+    done
+  fi
+  __r_emit "$__r_words"
+fi
+printf '%s\n' "$__r_out"
@@ -0,0 +1,589 @@
+use clap::Command;
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::fmt::Write;
+
+use crate::parser::{
+    ArgType, Argument, ArgumentParser, ArrayStyle, Error, Passthrough, Result, Subcommand,
+    MAGIC_PROG_NAME,
+};
+use crate::shell::{is_valid_name, quote, Shell, VarKind};
+
+/// The engine of the compiled parser, see [`compile`].
+const ENGINE: &str = include_str!("compile.bash");
+
+/// The tables describing the arguments, the (sub)commands and the required
+/// groups, which drive the engine. Each of them is a bash array, ex.
+/// `__r_along` holds the long name of each argument.
+const TABLES: &[&str] = &[
+    // The arguments, indexed in the order of the (sub)commands.
+    "akind",
+    "alvl",
+    "apos",
+    "amulti",
+    "areq",
+    "atype",
+    "aint",
+    "amin",
+    "amax",
+    "apossible",
+    "aenv",
+    "avar",
+    "adisp",
+    "areqdisp",
+    "aoptdisp",
+    "ashort",
+    "along",
+    "aconf",
+    "areqs",
+    "acap",
+    // The (sub)commands, the top-level command is the first one.
+    "lbin",
+    "lusename",
+    "lname",
+    "lnames",
+    "lsubs",
+    "lpass",
+    "largs",
+    "lpos",
+    "lusage",
+    "lsubnames",
+    // The choices and the defaults, each value along with its argument.
+    "chv",
+    "cho",
+    "dfv",
+    "dfo",
+    // The required groups, along with their (sub)commands.
+    "rgl",
+    "rgm",
+    "rgd",
+    // The help of the help subcommands, ex. `help help apply`, keyed by the
+    // (sub)command and the words after `help help`.
+    "hkey",
+    "htext",
+    "herr",
+];
+
+/// Stands for the unrecognized subcommand in the messages of the help
+/// subcommands, which is replaced by the engine.
+const UNRECOGNIZED: &str = "__RAMEN_SUBCOMMAND__";
+
+/// Compile the spec into a standalone bash function, which parses the
+/// arguments the same as ramen does, without ramen installed, ex.
+///
+/// ```bash
+/// ramen compile --spec-file upload.yaml > upload_args.bash
+/// source upload_args.bash
+/// eval "$(ramen_parse "$@")"
+/// ```
+///
+/// The function prints the script that ramen would print, so it's used the
+/// same way. The script is always written in bash, since the function runs
+/// in bash (3.2 or later), so specs for the other shells are rejected.
+pub fn compile(parser: &ArgumentParser, function: &str) -> Result<String> {
+    if !is_valid_name(function) {
+        return Err(Error::InvalidFunctionName {
+            name: function.to_owned(),
+        });
+    }
+    if parser.shell() != Shell::Bash {
+        return Err(Error::UnsupportedCompile {
+            shell: parser.shell(),
+        });
+    }
+    let mut command = parser.build_clap_command()?;
+    let mut compiler = Compiler {
+        parser,
+        tables: TABLES.iter().map(|x| (*x, Vec::new())).collect(),
+        help: Vec::new(),
+        args: 0,
+    };
+    let program = parser.program().to_owned();
+    compiler.add_command(&parser.root(), &mut command, &[], &program)?;
+
+    let mut script = format!(
+        "# The argument parser of {}, compiled from its spec by ramen {}.\n",
+        parser.program(),
+        env!("CARGO_PKG_VERSION")
+    );
+    writeln!(script, "# Usage: eval \"$({function} \"$@\")\"")?;
+    writeln!(script, "{function}() (")?;
+    for name in TABLES {
+        let words = &compiler.tables[name];
+        writeln!(script, "__r_{name}=({})", words.join(" "))?;
+    }
+    let declaration = parser.declaration();
+    let keyword = |kind| {
+        let assignment = Shell::Bash.assign(declaration, kind, "", "");
+        quote(OsStr::new(assignment.trim_end_matches('=')))
+    };
+    writeln!(script, "__r_kw_s={}", keyword(VarKind::String))?;
+    writeln!(script, "__r_kw_i={}", keyword(VarKind::Integer))?;
+    let array = Shell::Bash
+        .assign_array(declaration, "", &[])
+        .unwrap_or_default();
+    writeln!(
+        script,
+        "__r_kw_a={}",
+        quote(OsStr::new(array.trim_end_matches("=()")))
+    )?;
+    let indexed = parser.array_style() == ArrayStyle::Indexed;
+    writeln!(script, "__r_indexed={}", u8::from(indexed))?;
+    let command_variable = if parser.commands().is_empty() {
+        String::new()
+    } else {
+        parser.command_variable_name()
+    };
+    writeln!(
+        script,
+        "__r_cmdvar={}",
+        quote(OsStr::new(&command_variable))
+    )?;
+
+    script.push_str("__r_help_text() {\n  case $1 in\n");
+    for (level, text) in compiler.help.iter().enumerate() {
+        writeln!(script, "  {level}) __r_text={text} ;;")?;
+    }
+    script.push_str("  esac\n}\n");
+    script.push_str(ENGINE);
+    script.push_str(")\n");
+    Ok(script)
+}
+
+struct Compiler<'p> {
+    parser: &'p ArgumentParser,
+    tables: HashMap<&'static str, Vec<String>>,
+    /// The help message of each (sub)command, as a bash word.
+    help: Vec<String>,
+    /// The number of arguments added so far.
+    args: usize,
+}
+
+impl Compiler<'_> {
+    fn push(&mut self, table: &'static str, value: impl AsRef<str>) {
+        let word = quote(OsStr::new(value.as_ref()));
+        self.tables
+            .get_mut(table)
+            .expect("unknown table")
+            .push(word);
+    }
+
+    /// Add the (sub)command and its descendants, returns its index. The
+    /// words are the names of its parents, which select it in the command
+    /// line, and the usage name is how clap shows it in the usage, ex.
+    /// `deploy <TARGET> apply`.
+    fn add_command(
+        &mut self,
+        def: &Subcommand,
+        command: &mut Command,
+        parents: &[String],
+        usage_name: &str,
+    ) -> Result<usize> {
+        let level = self.help.len();
+        let bin = command
+            .get_bin_name()
+            .unwrap_or(command.get_name())
+            .to_owned();
+        let mut words = parents.to_vec();
+        if let Some(name) = def.name() {
+            words.push(name.to_owned());
+        }
+        self.help.push(self.help_text(def, &words)?);
+
+        let mut names = vec![def.name().unwrap_or_default()];
+        names.extend(def.aliases());
+        let usage = command.render_usage().to_string();
+        let usage = usage.strip_prefix("Usage: ").unwrap_or(&usage).to_owned();
+        let passthrough = match def.passthrough() {
+            Passthrough::None => "none",
+            Passthrough::Trailing => "trailing",
+            Passthrough::Unknown => "unknown",
+        };
+        self.push("lbin", &bin);
+        self.push("lusename", usage_name);
+        self.push("lname", def.name().unwrap_or_default());
+        self.push("lnames", names.join(" "));
+        self.push("lpass", passthrough);
+        self.push("lusage", usage);
+
+        let (args, positionals, required) = self.add_args(def, command, level)?;
+        self.push("largs", join(&args));
+        self.push("lpos", join(&positionals));
+
+        let mut subnames = Vec::new();
+        for subcommand in def.commands().iter() {
+            subnames.push(subcommand.name().unwrap_or_default());
+            subnames.extend(subcommand.aliases());
+        }
+        if !subnames.is_empty() {
+            subnames.push("help");
+        }
+        self.push("lsubnames", subnames.join(", "));
+        self.push("lsubs", "");
+
+        // The required arguments are shown between the names of the
+        // command and its subcommands.
+        let mut subs = Vec::new();
+        for subcommand in def.commands().iter() {
+            let name = subcommand.name().unwrap_or_default();
+            let Some(child) = command.find_subcommand_mut(name) else {
+                continue;
+            };
+            let usage_name = [bin.as_str()]
+                .into_iter()
+                .chain(required.iter().map(String::as_str))
+                .chain([name])
+                .collect::<Vec<_>>()
+                .join(" ");
+            subs.push(self.add_command(subcommand, child, &words, &usage_name)?);
+        }
+        let lsubs = self.tables.get_mut("lsubs").expect("unknown table");
+        lsubs[level] = quote(OsStr::new(&join(&subs)));
+        if !def.commands().is_empty() {
+            self.add_help_command(level, &words, &[], Some(def))?;
+        }
+        Ok(level)
+    }
+
+    /// Add the help subcommand of the help subcommand, ex. `help help`, and
+    /// the copies of the subcommands under it, which print their help.
+    fn add_help_command(
+        &mut self,
+        level: usize,
+        words: &[String],
+        path: &[&str],
+        def: Option<&Subcommand>,
+    ) -> Result<()> {
+        let mut optstring = vec![MAGIC_PROG_NAME.to_owned()];
+        optstring.extend(words.iter().cloned());
+        optstring.extend(
+            ["help", "help"]
+                .into_iter()
+                .chain(path.iter().copied())
+                .map(String::from),
+        );
+        let text = self.clap_message(optstring.clone())?;
+        optstring.push(UNRECOGNIZED.to_owned());
+        let unrecognized = self.clap_message(optstring)?;
+        let key = [level.to_string()]
+            .into_iter()
+            .chain(path.iter().map(|x| x.to_string()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.push("hkey", key);
+        self.push("htext", text);
+        self.push("herr", unrecognized);
+
+        let Some(def) = def else {
+            return Ok(());
+        };
+        for subcommand in def.commands().iter() {
+            let name = subcommand.name().unwrap_or_default();
+            let path = [path, &[name]].concat();
+            self.add_help_command(level, words, &path, Some(subcommand))?;
+        }
+        if path.is_empty() {
+            self.add_help_command(level, words, &["help"], None)?;
+        }
+        Ok(())
+    }
+
+    /// The message clap prints for the command line, ex. a help message.
+    fn clap_message(&self, optstring: Vec<String>) -> Result<String> {
+        Ok(
+            match self
+                .parser
+                .build_clap_command()?
+                .try_get_matches_from(optstring)
+            {
+                Err(err) => Error::from(err).to_string(),
+                Ok(_) => String::new(),
+            },
+        )
+    }
+
+    /// Add the arguments of the (sub)command, returns the indexes of all of
+    /// them, the ones of the positional arguments, and how the required ones
+    /// are shown in the usage.
+    fn add_args(
+        &mut self,
+        def: &Subcommand,
+        command: &Command,
+        level: usize,
+    ) -> Result<(Vec<usize>, Vec<usize>, Vec<String>)> {
+        let args = def.args();
+        let first = self.args;
+        let ids = args.iter().map(|x| x.id()).collect::<Result<Vec<_>>>()?;
+        let index_of = |id: &str| ids.iter().position(|x| x == id).map(|x| first + x);
+        let display = |id: &str| {
+            command
+                .get_arguments()
+                .find(|x| x.get_id() == id)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| id.to_owned())
+        };
+        // Required options first, then the required groups and positionals.
+        let mut required: Vec<String> = args
+            .iter()
+            .zip(ids.iter())
+            .filter(|(arg, _)| arg.is_required() && !arg.is_positional())
+            .map(|(_, id)| display(id))
+            .collect();
+        let mut required_groups = Vec::new();
+
+        // Conflicts are symmetric, and the arguments of a group which
+        // doesn't allow multiple ones conflict with each other.
+        let mut conflicts = vec![BTreeSet::new(); args.len()];
+        for (i, arg) in args.iter().enumerate() {
+            for id in arg.conflicts_with() {
+                if let Some(j) = index_of(id) {
+                    conflicts[i].insert(j);
+                    conflicts[j - first].insert(first + i);
+                }
+            }
+        }
+        for group in def.groups().iter() {
+            let members: Vec<usize> = group.args().into_iter().filter_map(index_of).collect();
+            if !group.is_multiple() {
+                for &i in members.iter() {
+                    for &j in members.iter().filter(|&&j| j != i) {
+                        conflicts[i - first].insert(j);
+                    }
+                }
+            }
+            if group.is_required() {
+                // Positional arguments are shown by their names, ex. `<SRC|--url <url>>`.
+                let names: Vec<String> = members
+                    .iter()
+                    .map(|&i| match &args[i - first] {
+                        arg if arg.is_positional() => ids[i - first].clone(),
+                        _ => display(&ids[i - first]),
+                    })
+                    .collect();
+                let name = format!("<{}>", names.join("|"));
+                self.push("rgl", level.to_string());
+                self.push("rgm", join(&members));
+                self.push("rgd", &name);
+                required_groups.push((name, members));
+            }
+        }
+        let grouped: Vec<usize> = required_groups
+            .iter()
+            .flat_map(|(_, members)| members.iter().copied())
+            .collect();
+        required.retain(|x| {
+            !grouped
+                .iter()
+                .any(|&i| !args[i - first].is_positional() && display(&ids[i - first]) == *x)
+        });
+        required.extend(required_groups.into_iter().map(|(name, _)| name));
+
+        let mut indexes = Vec::with_capacity(args.len());
+        let mut positionals = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            let index = first + i;
+            indexes.push(index);
+            if arg.is_positional() {
+                positionals.push(index);
+                if arg.is_required() && !grouped.contains(&index) {
+                    required.push(positional_usage(&ids[i], arg, true));
+                }
+            }
+            self.add_arg(arg, level, &ids[i], &display(&ids[i]))?;
+            let conflicts: Vec<usize> = conflicts[i].iter().copied().collect();
+            self.push("aconf", join(&conflicts));
+            let requires: Vec<usize> = arg.requires().into_iter().filter_map(index_of).collect();
+            self.push("areqs", join(&requires));
+        }
+        Ok((indexes, positionals, required))
+    }
+
+    fn add_arg(&mut self, arg: &Argument, level: usize, id: &str, display: &str) -> Result<()> {
+        let index = self.args;
+        self.args += 1;
+        let typ = arg.arg_type();
+        let kind = match typ {
+            ArgType::Boolean => "flag",
+            ArgType::Count => "count",
+            _ => "value",
+        };
+        // The value of a numeric argument is validated unless it's one of
+        // the choices.
+        let numeric = typ.is_numeric() && arg.select().is_none();
+        let number = |x: Option<f64>| match x {
+            Some(x) if numeric => x.to_string(),
+            _ => String::new(),
+        };
+        let cap = match (typ, arg.max()) {
+            (ArgType::Count, Some(max)) => (max.max(0.0) as u8).to_string(),
+            _ => String::new(),
+        };
+        // The environment variable can only be read in bash with a valid name.
+        let env = self
+            .parser
+            .env_name(arg)?
+            .filter(|x| is_valid_name(x))
+            .unwrap_or_default();
+        let choices = arg.select().unwrap_or_default();
+
+        self.push("akind", kind);
+        self.push("alvl", level.to_string());
+        self.push("apos", bit(arg.is_positional()));
+        self.push("amulti", bit(arg.is_multiple()));
+        self.push("areq", bit(arg.is_required()));
+        self.push(
+            "atype",
+            if numeric {
+                typ.to_string()
+            } else {
+                String::new()
+            },
+        );
+        // The choices of a select are emitted as strings, see `write_args`.
+        self.push(
+            "aint",
+            bit(typ == ArgType::Integer && arg.select().is_none()),
+        );
+        self.push("amin", number(arg.min()));
+        self.push("amax", number(arg.max()));
+        self.push("apossible", choices.join(", "));
+        self.push("aenv", env);
+        self.push("avar", self.parser.variable_name(arg)?);
+        self.push("adisp", display);
+        if arg.is_positional() {
+            self.push("areqdisp", positional_usage(id, arg, true));
+            self.push("aoptdisp", positional_usage(id, arg, false));
+        } else {
+            self.push("areqdisp", display);
+            self.push("aoptdisp", display);
+        }
+        self.push("ashort", arg.short().map(String::from).unwrap_or_default());
+        self.push("along", arg.long().unwrap_or_default());
+        self.push("acap", cap);
+        for choice in choices.iter() {
+            self.push("chv", choice);
+            self.push("cho", index.to_string());
+        }
+        let defaults = if arg.is_multiple() {
+            arg.defaults()
+        } else {
+            arg.default().into_iter().collect()
+        };
+        for value in defaults {
+            self.push("dfv", value);
+            self.push("dfo", index.to_string());
+        }
+        Ok(())
+    }
+
+    /// The help message of the (sub)command rendered by clap, as a bash word.
+    /// The values of the environment variables shown in the message are read
+    /// when the help is printed, rather than when the spec is compiled.
+    fn help_text(&self, def: &Subcommand, words: &[String]) -> Result<String> {
+        let mut optstring = vec![MAGIC_PROG_NAME.to_owned()];
+        optstring.extend(words.iter().cloned());
+        optstring.push("--help".to_owned());
+        let message = self.clap_message(optstring)?;
+
+        let mut needles = Vec::new();
+        for arg in def.args().iter() {
+            if let Some(env) = self.parser.env_name(arg)?.filter(|x| is_valid_name(x)) {
+                let value = std::env::var_os(&env).unwrap_or_default();
+                let needle = format!("[env: {env}={}]", value.to_string_lossy());
+                needles.push((needle, env));
+            }
+        }
+        let mut word = String::new();
+        let mut rest = message.as_str();
+        // Splice the variables in, from the first one shown in the message.
+        while let Some((start, needle, env)) = needles
+            .iter()
+            .filter_map(|(needle, env)| rest.find(needle.as_str()).map(|x| (x, needle, env)))
+            .min_by_key(|x| x.0)
+        {
+            if start > 0 {
+                word.push_str(&quote(OsStr::new(&rest[..start])));
+            }
+            write!(word, "\"[env: {env}=${{{env}-}}]\"")?;
+            rest = &rest[start + needle.len()..];
+        }
+        if word.is_empty() || !rest.is_empty() {
+            word.push_str(&quote(OsStr::new(rest)));
+        }
+        Ok(word)
+    }
+}
+
+/// How the positional argument is shown in the usage, ex. `<SRC>` if it's
+/// required, `[SRC]` if not, and `<FILES>...` if it's variadic.
+fn positional_usage(id: &str, arg: &Argument, required: bool) -> String {
+    let ellipsis = if arg.is_multiple() { "..." } else { "" };
+    if required {
+        format!("<{id}>{ellipsis}")
+    } else {
+        format!("[{id}]{ellipsis}")
+    }
+}
+
+fn bit(value: bool) -> &'static str {
+    if value {
+        "1"
+    } else {
+        "0"
+    }
+}
+
+fn join(indexes: &[usize]) -> String {
+    indexes
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::compile;
+    use crate::parser::{ArgumentParser, Error};
+    use crate::shell::Shell;
+
+    const SPEC: &str = r#"
+version: "1.0.0"
+program: upload
+args:
+  - SRC
+  - name: protocol
+    long: --protocol
+    select: [scp, "it's"]
+"#;
+
+    #[test]
+    fn test_compile() {
+        let parser = ArgumentParser::from_yaml(SPEC).unwrap();
+        let script = compile(&parser, "parse_upload").unwrap();
+        assert!(script.contains("\nparse_upload() (\n"), "{script}");
+        assert!(script.contains("__r_along=('' 'protocol')"), "{script}");
+        assert!(script.contains("__r_chv=('scp' 'it'\\''s')"), "{script}");
+        assert!(script.ends_with(")\n"), "{script}");
+    }
+
+    #[test]
+    fn test_compile_invalid_function_name() {
+        let parser = ArgumentParser::from_yaml(SPEC).unwrap();
+        let err = compile(&parser, "parse-upload").unwrap_err();
+        assert_eq!(
+            "invalid function name \"parse-upload\", must be a valid shell name",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_compile_other_shell() {
+        let parser = ArgumentParser::from_yaml(&format!("{SPEC}shell: fish\n")).unwrap();
+        let err = compile(&parser, "parse_upload").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnsupportedCompile { shell: Shell::Fish }
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+use clap_complete::Generator;
+
+use crate::parser::{ArgumentParser, Error, Result};
+use crate::shell::Shell;
+
+/// Generate the script of the given shell which completes the arguments of
+/// the program defined by the spec, ex. for bash:
+///
+/// ```bash
+/// ramen completions --shell bash --spec-file upload.yaml > /etc/bash_completion.d/upload
+/// ```
+pub fn generate(parser: &ArgumentParser, shell: Shell) -> Result<String> {
+    let generator = match shell {
+        Shell::Bash => clap_complete::Shell::Bash,
+        Shell::Zsh => clap_complete::Shell::Zsh,
+        Shell::Fish => clap_complete::Shell::Fish,
+        Shell::Sh => return Err(Error::UnsupportedCompletion { shell }),
+    };
+    // The command is built already, as the generator expects.
+    let command = parser.build_clap_command()?;
+    let mut script = Vec::new();
+    generator.generate(&command, &mut script);
+    Ok(String::from_utf8_lossy(&script).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::generate;
+    use crate::parser::{ArgumentParser, Error};
+    use crate::shell::Shell;
+
+    const SPEC: &str = r#"
+version: "1.0.0"
+program: upload
+args:
+  - SRC
+  - -t/--threads
+  - name: protocol
+    long: --protocol
+    select: [scp, rsync]
+commands:
+  - name: apply
+    args: [--force]
+"#;
+
+    #[test]
+    fn test_generate() {
+        let parser = ArgumentParser::from_yaml(SPEC).unwrap();
+
+        let bash = generate(&parser, Shell::Bash).unwrap();
+        assert!(bash.contains("complete -F _upload"), "{bash}");
+        assert!(bash.contains("--threads"), "{bash}");
+        assert!(bash.contains("scp rsync"), "{bash}");
+
+        let fish = generate(&parser, Shell::Fish).unwrap();
+        assert!(fish.contains("complete -c upload"), "{fish}");
+        assert!(fish.contains("-l force"), "{fish}");
+
+        let zsh = generate(&parser, Shell::Zsh).unwrap();
+        assert!(zsh.starts_with("#compdef upload"), "{zsh}");
+
+        assert!(matches!(
+            generate(&parser, Shell::Sh),
+            Err(Error::UnsupportedCompletion { shell: Shell::Sh })
+        ));
+    }
+}
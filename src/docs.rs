@@ -0,0 +1,239 @@
+use clap::{Command, ValueEnum};
+use std::fmt::Write;
+
+use crate::parser::{ArgType, Argument, ArgumentParser, Error, Passthrough, Result, Subcommand};
+
+/// The format of the reference documentation generated by [`generate`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DocFormat {
+    /// A Markdown document, ex. to be included in the README of the script.
+    #[default]
+    Markdown,
+}
+
+/// Generate the reference documentation of the program defined by the spec.
+pub fn generate(parser: &ArgumentParser, format: DocFormat) -> Result<String> {
+    match format {
+        DocFormat::Markdown => markdown(parser),
+    }
+}
+
+/// Generate the man page of the program defined by the spec, in roff.
+pub fn man(parser: &ArgumentParser) -> Result<String> {
+    let command = parser.build_clap_command()?;
+    let mut page = Vec::new();
+    clap_mangen::Man::new(command)
+        .render(&mut page)
+        .map_err(Error::RenderMan)?;
+    Ok(String::from_utf8_lossy(&page).into_owned())
+}
+
+/// Generate a Markdown document with the usage and the arguments of the
+/// program and each of its subcommands.
+fn markdown(parser: &ArgumentParser) -> Result<String> {
+    let mut command = parser.build_clap_command()?;
+    let mut doc = format!("# {}\n", parser.program());
+    if !parser.about().is_empty() {
+        write!(doc, "\n{}\n", parser.about())?;
+    }
+    write_command(&mut doc, parser, &parser.root(), &mut command)?;
+    Ok(doc)
+}
+
+fn write_command(
+    doc: &mut String,
+    parser: &ArgumentParser,
+    def: &Subcommand,
+    command: &mut Command,
+) -> Result<()> {
+    let usage = command.render_usage().to_string();
+    let usage = usage.strip_prefix("Usage: ").unwrap_or(&usage);
+    write!(doc, "\n```text\n{usage}\n```\n")?;
+
+    let args = def.args();
+    if !args.is_empty() || def.passthrough() != Passthrough::None {
+        doc.push_str("\n| Argument | Type | Default | Choices | Env | Description |\n");
+        doc.push_str("| --- | --- | --- | --- | --- | --- |\n");
+    }
+    for arg in args.iter() {
+        let defaults = arg.defaults();
+        let choices = arg.select().unwrap_or_default();
+        writeln!(
+            doc,
+            "| {} | {} | {} | {} | {} | {} |",
+            code(&arg_usage(arg)?),
+            type_name(arg),
+            code_list(defaults.iter().copied()),
+            code_list(choices.iter().map(String::as_str)),
+            code_list(parser.env_name(arg)?.as_deref()),
+            escape(arg.help().unwrap_or_default()),
+        )?;
+    }
+    let passthrough = match def.passthrough() {
+        Passthrough::None => None,
+        Passthrough::Trailing => Some("[-- ARGS...]"),
+        Passthrough::Unknown => Some("[ARGS...]"),
+    };
+    if let Some(usage) = passthrough {
+        writeln!(
+            doc,
+            "| {} | string |  |  |  | Arguments passed through to the script |",
+            code(usage)
+        )?;
+    }
+
+    for subcommand in def.commands().iter() {
+        let name = subcommand.name().unwrap_or_default();
+        let Some(child) = command.find_subcommand_mut(name) else {
+            continue;
+        };
+        let path = child.get_bin_name().unwrap_or(name).to_owned();
+        write!(doc, "\n## {path}\n")?;
+        if !subcommand.aliases().is_empty() {
+            write!(doc, "\nAliases: {}\n", code_list(subcommand.aliases()))?;
+        }
+        if let Some(about) = subcommand.about() {
+            write!(doc, "\n{about}\n")?;
+        }
+        write_command(doc, parser, subcommand, child)?;
+    }
+    Ok(())
+}
+
+/// How the argument is given in the command line, ex. `-t, --threads <threads>`,
+/// `<SRC>`, `[FILES]...`.
+fn arg_usage(arg: &Argument) -> Result<String> {
+    let id = arg.id()?;
+    let ellipsis = if arg.is_multiple() { "..." } else { "" };
+    if arg.is_positional() {
+        let name = arg.positional_name().unwrap_or(&id);
+        return Ok(if arg.is_required() {
+            format!("<{name}>{ellipsis}")
+        } else {
+            format!("[{name}]{ellipsis}")
+        });
+    }
+
+    let names: Vec<String> = arg
+        .short()
+        .map(|x| format!("-{x}"))
+        .into_iter()
+        .chain(arg.long().map(|x| format!("--{x}")))
+        .collect();
+    let mut usage = names.join(", ");
+    if !matches!(arg.arg_type(), ArgType::Boolean | ArgType::Count) {
+        write!(usage, " <{id}>{ellipsis}")?;
+    }
+    Ok(usage)
+}
+
+/// The type of the argument with its range, ex. `integer (1..=64)`.
+fn type_name(arg: &Argument) -> String {
+    let typ = arg.arg_type();
+    match (arg.min(), arg.max()) {
+        (Some(min), Some(max)) => format!("{typ} ({min}..={max})"),
+        (Some(min), None) => format!("{typ} (>= {min})"),
+        (None, Some(max)) => format!("{typ} (<= {max})"),
+        (None, None) => typ.to_string(),
+    }
+}
+
+fn code(text: &str) -> String {
+    format!("`{}`", escape(text))
+}
+
+fn code_list<'a>(items: impl IntoIterator<Item = &'a str>) -> String {
+    items.into_iter().map(code).collect::<Vec<_>>().join(", ")
+}
+
+/// Escape the text in a table cell.
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate, man, DocFormat};
+    use crate::parser::ArgumentParser;
+
+    const SPEC: &str = r#"
+version: "1.0.0"
+program: deploy
+about: Deploy the app.
+env_prefix: DEPLOY_
+args:
+  - TARGET
+  - "[FILES...]"
+  - name: threads
+    short: -t
+    long: --threads
+    type: integer
+    default: 8
+    min: 1
+    max: 64
+    help: Number of threads.
+  - name: protocol
+    long: --protocol
+    default: scp
+    select: [scp, rsync]
+    env: PROTOCOL
+commands:
+  - name: apply
+    about: Apply the changes.
+    aliases: [up]
+    passthrough: trailing
+    args:
+      - name: force
+        long: --force
+        type: boolean
+        help: Skip the prompt | confirm.
+"#;
+
+    #[test]
+    fn test_markdown() {
+        let parser = ArgumentParser::from_yaml(SPEC).unwrap();
+        assert_eq!(
+            r#"# deploy
+
+Deploy the app.
+
+```text
+deploy [OPTIONS] <TARGET> [FILES]... <COMMAND>
+```
+
+| Argument | Type | Default | Choices | Env | Description |
+| --- | --- | --- | --- | --- | --- |
+| `<TARGET>` | string |  |  | `DEPLOY_TARGET` |  |
+| `[FILES]...` | string |  |  | `DEPLOY_FILES` |  |
+| `-t, --threads <threads>` | integer (1..=64) | `8` |  | `DEPLOY_THREADS` | Number of threads. |
+| `--protocol <protocol>` | string | `scp` | `scp`, `rsync` | `PROTOCOL` |  |
+
+## deploy apply
+
+Aliases: `up`
+
+Apply the changes.
+
+```text
+deploy <TARGET> apply [OPTIONS] [-- [ARGS]...]
+```
+
+| Argument | Type | Default | Choices | Env | Description |
+| --- | --- | --- | --- | --- | --- |
+| `--force` | boolean |  |  | `DEPLOY_FORCE` | Skip the prompt \| confirm. |
+| `[-- ARGS...]` | string |  |  |  | Arguments passed through to the script |
+"#,
+            generate(&parser, DocFormat::Markdown).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_man() {
+        let parser = ArgumentParser::from_yaml(SPEC).unwrap();
+        let page = man(&parser).unwrap();
+        assert!(page.starts_with(".ie \\n(.g .ds Aq \\(aq"), "{page}");
+        assert!(page.contains(".TH deploy 1"), "{page}");
+        assert!(page.contains("Number of threads."), "{page}");
+        assert!(page.contains("deploy\\-apply"), "{page}");
+    }
+}
@@ -1,3 +1,11 @@
+pub mod compile;
+pub mod completion;
+pub mod docs;
+pub mod lint;
 pub mod parser;
 pub use parser::parse;
+pub mod script;
+pub mod shell;
+pub mod source;
+pub mod spec;
 pub mod version;
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::parser::{number_value_parser, ArgType, Argument, ArgumentParser, Result, Subcommand};
+use crate::source::Location;
+
+/// A problem of the spec found by [`lint`], which would otherwise surface
+/// only when parsing the arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    /// The path of the key of the problem, ex. `args[1].default`.
+    pub path: String,
+    pub message: String,
+    /// Where the key is in the spec document, if known.
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, (key: {})", self.message, self.path)?;
+        if let Some(location) = &self.location {
+            write!(f, "\n{location}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the spec of the parser without parsing any arguments. The spec
+/// has been validated when the parser was built, ex. duplicate names of the
+/// arguments and positionals which never get a value are errors already, so
+/// this looks for the problems of the defaults, which only surface when the
+/// arguments are absent:
+///
+/// - defaults which aren't one of the choices of `select`
+/// - defaults of numeric arguments which aren't valid numbers
+/// - defaults of counting flags which aren't valid counts
+///
+/// Once there are none, the references between the arguments are checked by
+/// building the clap command, and the errors of it are returned.
+pub fn lint(parser: &ArgumentParser) -> Result<Vec<Lint>> {
+    let mut linter = Linter {
+        parser,
+        lints: vec![],
+    };
+    linter.check_command(&parser.root());
+    if linter.lints.is_empty() {
+        parser.build_clap_command()?;
+    }
+    Ok(linter.lints)
+}
+
+struct Linter<'a> {
+    parser: &'a ArgumentParser,
+    lints: Vec<Lint>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, path: String, message: String) {
+        self.lints.push(Lint {
+            location: self.parser.location(&path),
+            path,
+            message,
+        });
+    }
+
+    fn check_command(&mut self, command: &Subcommand) {
+        for arg in command.args().iter() {
+            self.check_defaults(arg);
+        }
+
+        for subcommand in command.commands().iter() {
+            self.check_command(subcommand);
+        }
+    }
+
+    fn check_defaults(&mut self, arg: &Argument) {
+        let typ = arg.arg_type();
+        let parse_number = number_value_parser(typ, arg.min(), arg.max());
+        for (i, default) in arg.defaults().into_iter().enumerate() {
+            let path = format!("{}.default[{i}]", arg.path());
+            if let Some(choices) = arg.select() {
+                if !choices.iter().any(|x| x == default) {
+                    let message = format!("default {default:?} is not one of {choices:?}");
+                    self.report(path, message);
+                    continue;
+                }
+            }
+            if typ.is_numeric() {
+                if let Err(message) = parse_number(default) {
+                    self.report(path, format!("invalid default, {message}"));
+                }
+            } else if typ == ArgType::Count && default.parse::<u8>().is_err() {
+                let message = format!("invalid default, {default:?} is not a valid count");
+                self.report(path, message);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::lint;
+    use crate::parser::{ArgumentParser, Error};
+
+    fn lint_messages(spec: &str) -> Vec<String> {
+        let parser = ArgumentParser::from_yaml(spec).unwrap();
+        let lints = lint(&parser).unwrap();
+        lints.into_iter().map(|x| x.message).collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let messages = lint_messages(
+            r#"
+version: "1.0.0"
+program: upload
+args:
+  - name: threads
+    long: --threads
+    type: integer
+    default: eight
+  - name: tries
+    short: -t
+    type: integer
+    default: 0
+    min: 1
+  - name: protocol
+    long: --protocol
+    default: ftp
+    select: [scp, rsync]
+commands:
+  - name: apply
+    args:
+      - name: verbose
+        short: -v
+        type: count
+        default: abc
+"#,
+        );
+        assert_eq!(
+            vec![
+                "invalid default, \"eight\" is not a valid integer",
+                "invalid default, 0 is less than 1",
+                "default \"ftp\" is not one of [\"scp\", \"rsync\"]",
+                "invalid default, \"abc\" is not a valid count",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_lint_clean_spec() {
+        let spec = r#"
+version: "1.0.0"
+program: upload
+args:
+  - FILES...
+  - DST
+  - name: threads
+    long: --threads
+    type: integer
+    default: 8
+    select: ["4", "8"]
+  - name: verbose
+    short: -v
+    type: count
+    default: 2
+"#;
+        assert!(lint_messages(spec).is_empty());
+
+        let spec = "version: \"1.0.0\"\nprogram: upload\nargs:\n  - {name: x, requires: [y]}\n";
+        let parser = ArgumentParser::from_yaml(spec).unwrap();
+        let err = lint(&parser).unwrap_err();
+        assert!(matches!(
+            err.without_location(),
+            Error::UnknownReference { id, .. } if id == "y"
+        ));
+        assert_eq!(Some(4), err.location().map(|x| x.line));
+    }
+}
@@ -1,19 +1,46 @@
-use anyhow::{anyhow, Result};
-use clap::Parser;
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Parser, Subcommand};
 use log::LevelFilter;
+use ramen::compile::compile;
+use ramen::completion;
+use ramen::docs::{self, DocFormat};
+use ramen::lint::lint;
+use ramen::parser::{ArgumentParser, OutputFormat, ParseOptions};
+use ramen::script;
+use ramen::shell::{Declaration, Shell};
+use ramen::spec::SpecFormat;
+use std::ffi::OsString;
+use std::fs;
 use std::io::{self, Read};
+use std::path::PathBuf;
 const APP: &str = "ramen";
 const DESC_ABOUT: &str = "An easier way to define and parse arguments in SHELL scripts.";
 
 #[derive(Debug, Parser)]
 #[command(name=&APP, version, about=DESC_ABOUT, long_about=None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// A definition of the argument parser in YAML format.
-    #[arg()]
-    spec: Option<String>,
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    #[command(flatten)]
+    source: SpecSource,
+
+    /// The dialect of the output script, overrides the "shell" key of the spec.
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+
+    /// How the variables are declared, e.g. "local" when eval runs inside a
+    /// function, overrides the "declare" key of the spec.
+    #[arg(long, value_enum)]
+    declare: Option<Declaration>,
+
+    /// The format of the output, a script to eval, or a JSON object.
+    #[arg(long, value_enum, default_value_t)]
+    format: OutputFormat,
 
     /// Enable debug mode.
-    #[arg(short, long, value_name = "DEBUG")]
+    #[arg(short, long, value_name = "DEBUG", global = true)]
     debug: bool,
 
     /// The arguments to parse. Passed as the last argument, after a "--".
@@ -21,7 +48,81 @@ struct Cli {
     ///
     ///     eval "$( ramen "$program" -- "$@" )"
     #[arg(last = true)]
-    optstring: Vec<String>,
+    optstring: Vec<OsString>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Check the spec for mistakes without parsing any arguments.
+    Check {
+        #[command(flatten)]
+        source: SpecSource,
+    },
+
+    /// Generate a completion script for the program defined by the spec.
+    Completions {
+        /// The shell to complete in, defaults to the "shell" key of the spec.
+        #[arg(long, value_enum)]
+        shell: Option<Shell>,
+
+        #[command(flatten)]
+        source: SpecSource,
+    },
+
+    /// Generate the man page of the program defined by the spec.
+    Man {
+        #[command(flatten)]
+        source: SpecSource,
+    },
+
+    /// Generate the reference documentation of the program defined by the
+    /// spec, with the arguments, their types, defaults and subcommands.
+    Docs {
+        /// The format of the documentation.
+        #[arg(long, value_enum, default_value_t)]
+        format: DocFormat,
+
+        #[command(flatten)]
+        source: SpecSource,
+    },
+
+    /// Compile the spec into a standalone bash function, which parses the
+    /// arguments without ramen. e.g.
+    ///
+    ///     eval "$( ramen_parse "$@" )"
+    Compile {
+        /// The name of the function.
+        #[arg(long, value_name = "NAME", default_value = "ramen_parse")]
+        function: String,
+
+        #[command(flatten)]
+        source: SpecSource,
+    },
+}
+
+/// Where the spec is read from.
+#[derive(Debug, Args)]
+struct SpecSource {
+    /// A definition of the argument parser in YAML, JSON or TOML format.
+    #[arg()]
+    spec: Option<String>,
+
+    /// Read the spec from a file.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["spec", "from_script"])]
+    spec_file: Option<PathBuf>,
+
+    /// Read the spec embedded in a shell script, in a heredoc delimited by
+    /// "RAMEN", or in a comment block between "# ramen:begin" and
+    /// "# ramen:end". Usually it's "$0" in the bash script. e.g.
+    ///
+    ///     eval "$( ramen --from-script "$0" -- "$@" )"
+    #[arg(long, value_name = "PATH", conflicts_with = "spec")]
+    from_script: Option<PathBuf>,
+
+    /// The format of the spec, detected from the extension of the spec file,
+    /// or from the content by default.
+    #[arg(long, value_enum, default_value_t)]
+    spec_format: SpecFormat,
 }
 
 fn main() -> Result<()> {
@@ -32,23 +133,108 @@ fn main() -> Result<()> {
         LevelFilter::Warn
     });
 
+    match &cli.command {
+        Some(Commands::Check { source }) => return check(source),
+        Some(Commands::Completions { shell, source }) => {
+            let parser = load_parser(source)?;
+            let shell = shell.unwrap_or(parser.shell());
+            print!("{}", completion::generate(&parser, shell)?);
+            return Ok(());
+        }
+        Some(Commands::Man { source }) => {
+            print!("{}", docs::man(&load_parser(source)?)?);
+            return Ok(());
+        }
+        Some(Commands::Docs { format, source }) => {
+            print!("{}", docs::generate(&load_parser(source)?, *format)?);
+            return Ok(());
+        }
+        Some(Commands::Compile { function, source }) => {
+            print!("{}", compile(&load_parser(source)?, function)?);
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let parser = load_parser(&cli.source)?;
+    let options = ParseOptions {
+        shell: cli.shell,
+        declaration: cli.declare,
+        format: cli.format,
+    };
+    let output = match parser.parse(&cli.optstring, &options) {
+        Ok(output) => output,
+        Err(err) => match err.exit_code() {
+            // Help and usage errors are forwarded to the calling
+            // script, so that it prints the message and exits with the code.
+            Some(exit_code) if cli.format == OutputFormat::Shell => parser
+                .output_shell(&options)
+                .exit_script(&err.to_string(), err.use_stderr(), exit_code),
+            // Other consumers get the message and the exit code from ramen.
+            Some(exit_code) => {
+                if err.use_stderr() {
+                    eprint!("{err}");
+                } else {
+                    print!("{err}");
+                }
+                std::process::exit(exit_code);
+            }
+            None => return Err(err.into()),
+        },
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+/// Report the problems of the spec, see [`lint`].
+fn check(source: &SpecSource) -> Result<()> {
+    let parser = load_parser(source)?;
+    let lints = lint(&parser)?;
+    for lint in lints.iter() {
+        eprintln!("Error: {lint}");
+    }
+    if !lints.is_empty() {
+        return Err(anyhow!("found {} problem(s) in the spec", lints.len()));
+    }
+    Ok(())
+}
+
+/// Read and load the spec, in the format given or detected.
+fn load_parser(source: &SpecSource) -> Result<ArgumentParser> {
+    let spec = read_spec(source)?;
+    let format = match (source.spec_format, &source.spec_file) {
+        (SpecFormat::Auto, Some(path)) => SpecFormat::from_path(path).unwrap_or_default(),
+        (format, _) => format,
+    };
+    Ok(ArgumentParser::from_spec(&spec, format)?)
+}
+
+/// Read the spec from a file, a script, the command line or STDIN.
+fn read_spec(cli: &SpecSource) -> Result<String> {
+    if let Some(path) = &cli.spec_file {
+        return fs::read_to_string(path)
+            .with_context(|| format!("read spec file {}", path.display()));
+    }
+    if let Some(path) = &cli.from_script {
+        let content =
+            fs::read_to_string(path).with_context(|| format!("read script {}", path.display()))?;
+        return script::extract_spec(&content)?
+            .ok_or_else(|| anyhow!("no embedded spec found in {}", path.display()));
+    }
+
     let spec_from_pipe = read_spec_from_stdin()?;
-    let spec_from_arg = cli.spec.unwrap_or_default();
+    let spec_from_arg = cli.spec.clone().unwrap_or_default();
 
     // Stop working on data provided both through STDIN and CLI, avoid ambiguity.
-    if spec_from_pipe.len() > 0 && spec_from_arg.len() > 0 {
+    if !spec_from_pipe.is_empty() && !spec_from_arg.is_empty() {
         return Err(anyhow!("Error: both stdin and command-line argument were provided. Please use only one of them."));
     }
 
-    let spec = if spec_from_pipe.is_empty() {
+    Ok(if spec_from_pipe.is_empty() {
         spec_from_arg
     } else {
         spec_from_pipe
-    };
-
-    let output = ramen::parse(&spec, &cli.optstring)?;
-    println!("{}", output);
-    Ok(())
+    })
 }
 
 /// Read data from STDIN if provided.
@@ -1,17 +1,30 @@
-use clap::{Arg, ArgMatches, Command};
+use clap::builder::PossibleValuesParser;
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::{value_parser, Arg, ArgGroup, ArgMatches, Command, ValueEnum};
 use log::debug;
 use once_cell::sync::Lazy;
 use regex::{Match, Regex};
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::fmt::Write;
+use strum_macros::{Display, EnumString};
 use thiserror::Error;
-use yaml_rust::{ScanError, Yaml, YamlLoader};
+use yaml_rust::ScanError;
 
+use crate::shell::{self, Declaration, Shell, VarKind};
+use crate::source::{join_path, Location, SourceMap};
+use crate::spec::{ArgSpec, CommandSpec, DefaultValue, GroupSpec, Spec, SpecFormat};
 use crate::version::Version;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub const MAGIC_PROG_NAME: &str = "__RAMEN_PROG__";
 
+/// The id of the argument collecting the arguments to pass through.
+const PASSTHROUGH_ARG_ID: &str = "__RAMEN_PASSTHROUGH__";
+
 static REG_SHORT_LONG_ARG_NAME: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^-(?P<short>[a-zA-Z])(/--(?P<long>[a-zA-Z][a-zA-Z0-9-]{1,}))*|--(?P<only_long>[a-zA-Z][a-zA-Z0-9-]{1,})$").unwrap()
 });
@@ -21,12 +34,21 @@ pub enum Error {
     #[error("parse yaml error: {0}")]
     ParseYaml(#[from] ScanError),
 
+    #[error("parse json error: {0}")]
+    ParseJson(#[from] serde_json::Error),
+
+    #[error("parse toml error: {0}")]
+    ParseToml(#[from] toml::de::Error),
+
     #[error("no docs detected in the given yaml")]
     NoDocs,
 
     #[error("multi-docs detected in the given yaml, which is not supported")]
     MultiDocs,
 
+    #[error("{message}, (key: {path})")]
+    InvalidSpec { path: String, message: String },
+
     #[error(
         "invalid version, must be one of: {:?}, (key: version)",
         Version::supported_versions()
@@ -39,27 +61,285 @@ pub enum Error {
     #[error("missing argument name (key: args[].name)")]
     MissingArgumentName,
 
+    #[error("missing command name (key: commands[].name)")]
+    MissingCommandName,
+
+    #[error("missing group name (key: groups[].name)")]
+    MissingGroupName,
+
+    #[error("unknown argument {id:?}, (key: {key})")]
+    UnknownReference { key: &'static str, id: String },
+
+    #[error("invalid shell variable name {name:?} for argument {id:?}, (key: args[].var)")]
+    InvalidVariableName { id: String, name: String },
+
+    #[error("variable {name:?} is reserved by the shell, used by argument {id:?}")]
+    ReservedVariableName { id: String, name: String },
+
+    #[error("arguments {first:?} and {second:?} map to the same variable {name:?}")]
+    DuplicateVariableName {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("{key} can't be combined with select, list the allowed values in select instead, (key: args[].{key})")]
+    SelectWithRange { key: &'static str },
+
+    #[error("choice {choice:?} is not a valid {typ}, (key: args[].select)")]
+    InvalidChoice { choice: String, typ: ArgType },
+
+    #[error("argument {id:?} is defined more than once, (key: args[].name)")]
+    DuplicateArgument { id: String },
+
+    #[error("{name} is used by both {first:?} and {second:?}")]
+    DuplicateOptionName {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("{name} is reserved for the help, (key: {key})")]
+    ReservedHelpName { name: String, key: &'static str },
+
+    #[error("optional positional argument {optional:?} can't come before the required {required:?}, (key: args[].required)")]
+    OptionalBeforeRequired { optional: String, required: String },
+
+    #[error("positional argument {id:?} never gets a value after the variadic {variadic:?}, only a single required positional may follow it")]
+    PositionalAfterVariadic { id: String, variadic: String },
+
+    #[error("command name {name:?} is used more than once, (key: commands[].name)")]
+    DuplicateCommandName { name: String },
+
+    #[error("min is not supported by count arguments, they start at 0, (key: args[].min)")]
+    MinOnCount,
+
+    #[error("declaration {declaration:?} is not supported by {shell}")]
+    UnsupportedDeclaration {
+        shell: Shell,
+        declaration: Declaration,
+    },
+
+    #[error("completions are not supported for {shell}")]
+    UnsupportedCompletion { shell: Shell },
+
+    #[error("the compiled parser is written in bash, but the spec is for {shell}, (key: shell)")]
+    UnsupportedCompile { shell: Shell },
+
+    #[error("invalid function name {name:?}, must be a valid shell name")]
+    InvalidFunctionName { name: String },
+
+    #[error("the delimiter of the heredoc at line {line} must be quoted, ex. <<'RAMEN', otherwise the shell expands the `$`, backticks and backslashes of the spec")]
+    UnquotedHeredoc { line: usize },
+
     #[error(transparent)]
     Format(#[from] std::fmt::Error),
+
+    #[error("render man page error: {0}")]
+    RenderMan(#[source] std::io::Error),
+
+    /// An error of the spec along with where it is in the spec document.
+    #[error("{error}\n{location}")]
+    Located {
+        error: Box<Error>,
+        location: Location,
+    },
+
+    // The following errors are raised while parsing the optstring. Each of
+    // them carries the message rendered by clap and the exit code expected
+    // by the calling script.
+    #[error("{message}")]
+    DisplayHelp { message: String, exit_code: i32 },
+
+    #[error("{message}")]
+    UnknownArgument { message: String, exit_code: i32 },
+
+    #[error("{message}")]
+    MissingValue { message: String, exit_code: i32 },
+
+    #[error("{message}")]
+    InvalidValue { message: String, exit_code: i32 },
+
+    #[error("{message}")]
+    MissingRequiredArgument { message: String, exit_code: i32 },
+
+    #[error("{message}")]
+    ArgumentConflict { message: String, exit_code: i32 },
+
+    #[error("{message}")]
+    Usage { message: String, exit_code: i32 },
+}
+
+impl Error {
+    /// The exit code of an error raised while parsing the optstring, `None`
+    /// for the errors of the spec itself.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Error::DisplayHelp { exit_code, .. }
+            | Error::UnknownArgument { exit_code, .. }
+            | Error::MissingValue { exit_code, .. }
+            | Error::InvalidValue { exit_code, .. }
+            | Error::MissingRequiredArgument { exit_code, .. }
+            | Error::ArgumentConflict { exit_code, .. }
+            | Error::Usage { exit_code, .. } => Some(*exit_code),
+            _ => None,
+        }
+    }
+
+    /// The error without its location in the spec document.
+    pub fn without_location(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } => error,
+            _ => self,
+        }
+    }
+
+    /// The location of the error in the spec document, if known.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Located { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    fn at(self, location: Option<Location>) -> Self {
+        match location {
+            Some(location) => Error::Located {
+                error: Box::new(self),
+                location,
+            },
+            None => self,
+        }
+    }
+
+    /// Whether the message should be printed to stderr. Only the help
+    /// messages go to stdout.
+    pub fn use_stderr(&self) -> bool {
+        !matches!(self, Error::DisplayHelp { .. })
+    }
+}
+
+impl From<clap::Error> for Error {
+    fn from(err: clap::Error) -> Self {
+        let message = err.to_string();
+        let exit_code = err.exit_code();
+        // clap reports a missing value as an invalid empty value.
+        let is_missing_value = matches!(
+            err.get(ContextKind::InvalidValue),
+            Some(ContextValue::String(value)) if value.is_empty()
+        );
+        match err.kind() {
+            ErrorKind::DisplayHelp | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
+                Error::DisplayHelp { message, exit_code }
+            }
+            ErrorKind::UnknownArgument | ErrorKind::InvalidSubcommand => {
+                Error::UnknownArgument { message, exit_code }
+            }
+            ErrorKind::InvalidValue if is_missing_value => {
+                Error::MissingValue { message, exit_code }
+            }
+            ErrorKind::TooFewValues => Error::MissingValue { message, exit_code },
+            ErrorKind::InvalidValue | ErrorKind::ValueValidation | ErrorKind::InvalidUtf8 => {
+                Error::InvalidValue { message, exit_code }
+            }
+            ErrorKind::MissingRequiredArgument | ErrorKind::MissingSubcommand => {
+                Error::MissingRequiredArgument { message, exit_code }
+            }
+            ErrorKind::ArgumentConflict => Error::ArgumentConflict { message, exit_code },
+            _ => Error::Usage { message, exit_code },
+        }
+    }
 }
 
 pub struct ArgumentParser {
-    doc: Yaml,
+    spec: Spec,
+    source: SourceMap,
 }
 
 impl ArgumentParser {
     const LOG_TARGET: &str = "ArgumentParser";
 
-    pub fn new(doc: Yaml) -> Result<Self> {
-        debug!(target: Self::LOG_TARGET, "parse spec doc: {doc:?}");
-        let parser = Self { doc };
+    pub fn new(spec: Spec) -> Result<Self> {
+        Self::with_source(spec, SourceMap::default())
+    }
+
+    fn with_source(spec: Spec, source: SourceMap) -> Result<Self> {
+        debug!(target: Self::LOG_TARGET, "parse spec: {spec:?}");
+        let parser = Self { spec, source };
         parser.validate()?;
         Ok(parser)
     }
 
+    /// Build an ArgumentParser instance by parsing the given YAML spec.
+    pub fn from_yaml(spec_yaml: &str) -> Result<Self> {
+        Self::from_spec(spec_yaml, SpecFormat::Yaml)
+    }
+
+    /// Build an ArgumentParser instance by parsing the given spec in YAML,
+    /// JSON or TOML format, see [`SpecFormat`]. The errors of the spec are
+    /// reported with their locations in the document, see [`Error::Located`].
+    pub fn from_spec(spec: &str, format: SpecFormat) -> Result<Self> {
+        // JSON is a subset of YAML, so both are indexed by the YAML parser.
+        // The errors of TOML come with a snippet of the document already.
+        let source = match format.resolve(spec) {
+            SpecFormat::Toml => SourceMap::default(),
+            _ => SourceMap::new(spec),
+        };
+        let loaded = format.load(spec).map_err(|err| {
+            let location = match &err {
+                Error::ParseYaml(e) => {
+                    Location::new(spec, e.marker().line(), e.marker().col() + 1, 1)
+                }
+                Error::ParseJson(e) => Location::new(spec, e.line(), e.column(), 1),
+                Error::InvalidSpec { path, message } if message.starts_with("unknown field") => {
+                    source.locate_key(path)
+                }
+                Error::InvalidSpec { path, .. } => source.locate(path),
+                _ => None,
+            };
+            err.at(location)
+        })?;
+        Self::with_source(loaded, source)
+    }
+
+    /// Parse the optstring, and use the matches to compose the output script
+    /// (or the JSON object, see [`OutputFormat`]).
+    pub fn parse<I, T>(&self, optstring: I, options: &ParseOptions) -> Result<String>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let command = self.build_clap_command()?;
+        let optstring = normalize_optstring(optstring);
+        debug!(target: Self::LOG_TARGET, "OPTSTRING: {optstring:?}");
+        let matches = command.try_get_matches_from(optstring)?;
+        match options.format {
+            OutputFormat::Shell => {
+                let style = ScriptStyle {
+                    shell: self.output_shell(options),
+                    declaration: self.output_declaration(options),
+                    array_style: self.array_style(),
+                };
+                if !style.shell.supports(style.declaration) {
+                    return Err(Error::UnsupportedDeclaration {
+                        shell: style.shell,
+                        declaration: style.declaration,
+                    });
+                }
+                compose_shell_script(self, &matches, &style)
+            }
+            OutputFormat::Json => compose_json(self, &matches),
+        }
+    }
+
+    /// The spec of the parser.
+    pub fn spec(&self) -> &Spec {
+        &self.spec
+    }
+
     /// The version of the spec.
     pub fn version(&self) -> &str {
-        self.doc["version"].as_str().unwrap_or_default()
+        &self.spec.version
     }
 
     /// Convert the version number from string to `Version`.
@@ -69,7 +349,7 @@ impl ArgumentParser {
 
     /// The name of the program.
     pub fn program(&self) -> &str {
-        self.doc["program"].as_str().unwrap_or_default()
+        &self.spec.program
     }
 
     /// Add a prefix to the name of each argument in the output script.
@@ -77,68 +357,674 @@ impl ArgumentParser {
     /// the final output script will be `myapp_verbose=xxx`. By default,
     /// no prefix will be applied.
     pub fn output_prefix(&self) -> &str {
-        self.doc["output_prefix"].as_str().unwrap_or_default()
+        &self.spec.output_prefix
+    }
+
+    /// How multi-valued arguments are written in the output script, see
+    /// [`ArrayStyle`]. Defaults to native shell arrays.
+    pub fn array_style(&self) -> ArrayStyle {
+        self.spec.array_style
+    }
+
+    /// How the ids of the arguments are converted to variable names in the
+    /// output script, see [`NamingStrategy`]. Defaults to keep.
+    pub fn naming(&self) -> NamingStrategy {
+        self.spec.naming
+    }
+
+    /// The name of the variable of the argument in the output script, which
+    /// is the explicit `var` of the argument, or derived from its id by the
+    /// naming strategy, with the output prefix applied.
+    pub fn variable_name(&self, arg: &Argument) -> Result<String> {
+        let name = match arg.var() {
+            Some(var) => var.to_owned(),
+            None => self.naming().apply(&arg.id()?),
+        };
+        Ok(format!("{}{}", self.output_prefix(), name))
+    }
+
+    /// The name of the variable holding the path of the chosen subcommands.
+    pub fn command_variable_name(&self) -> String {
+        format!("{}command", self.output_prefix())
+    }
+
+    /// The dialect of the output script, see [`Shell`]. Defaults to bash.
+    pub fn shell(&self) -> Shell {
+        self.spec.shell
+    }
+
+    /// The dialect of the output script, the one given in the options takes
+    /// precedence over the one in the spec.
+    pub fn output_shell(&self, options: &ParseOptions) -> Shell {
+        options.shell.unwrap_or(self.shell())
+    }
+
+    /// How the variables are declared in the output script, see
+    /// [`Declaration`]. Defaults to plain assignments.
+    pub fn declaration(&self) -> Declaration {
+        self.spec.declare
+    }
+
+    /// How the variables are declared, the one given in the options takes
+    /// precedence over the one in the spec.
+    pub fn output_declaration(&self, options: &ParseOptions) -> Declaration {
+        options.declaration.unwrap_or(self.declaration())
+    }
+
+    /// A prefix to derive the environment variable of each argument which
+    /// doesn't have an explicit `env` key. For example, if a argument named
+    /// "threads", and prefix is "UPLOAD_", its value falls back to the
+    /// environment variable `UPLOAD_THREADS`.
+    pub fn env_prefix(&self) -> Option<&str> {
+        self.spec.env_prefix.as_deref()
+    }
+
+    /// The environment variable that the argument falls back to.
+    pub fn env_name(&self, arg: &Argument) -> Result<Option<String>> {
+        if let Some(env) = arg.env() {
+            return Ok(Some(env.to_owned()));
+        }
+        let Some(prefix) = self.env_prefix() else {
+            return Ok(None);
+        };
+        let name: String = arg
+            .id()?
+            .chars()
+            .map(|x| match x {
+                'a'..='z' | 'A'..='Z' | '0'..='9' => x.to_ascii_uppercase(),
+                _ => '_',
+            })
+            .collect();
+        Ok(Some(format!("{prefix}{name}")))
     }
 
     /// A description of the program.
     pub fn about(&self) -> &str {
-        self.doc["about"].as_str().unwrap_or_default()
+        self.spec.about.as_deref().unwrap_or_default()
     }
 
     /// Create a list of Argument instance by parsing the `args` definitions.
-    pub fn args(&self) -> Vec<Argument> {
-        self.doc["args"]
-            .as_vec()
-            .map(|vec| vec.iter().map(|item| Argument::new(item)).collect())
-            .unwrap_or_default()
+    pub fn args(&self) -> Vec<Argument<'_>> {
+        self.root().args()
+    }
+
+    /// The top-level command, whose args, groups and commands are defined at
+    /// the root of the spec.
+    pub fn root(&self) -> Subcommand<'_> {
+        Subcommand::root(&self.spec)
+    }
+
+    /// Create a list of Subcommand instance by parsing the `commands` definitions.
+    pub fn commands(&self) -> Vec<Subcommand<'_>> {
+        self.root().commands()
+    }
+
+    /// Where the given key is in the spec document, ex. `args[1].type`. The
+    /// nearest ancestor is used if the key is absent.
+    pub(crate) fn location(&self, path: &str) -> Option<Location> {
+        self.source.locate(path)
+    }
+
+    /// Attach the location of the given key of the spec to the error.
+    fn locate(&self, error: Error, path: &str) -> Error {
+        error.at(self.location(path))
     }
 
     pub fn build_clap_command(&self) -> Result<Command> {
-        let mut command = Command::new(self.program().to_owned()).about(self.about().to_owned());
+        // Set the bin name explicitly, otherwise the usage would be rendered
+        // with the dummy program name inserted by `normalize_optstring`.
+        let mut command =
+            Command::new(self.program().to_owned()).bin_name(self.program().to_owned());
+        if !self.about().is_empty() {
+            command = command.about(self.about().to_owned());
+        }
+
+        let mut command = self.build_clap_children(command, &self.root())?;
+        command.build();
+        Ok(command)
+    }
 
-        for arg in self.args().iter() {
-            debug!(target: Self::LOG_TARGET, "build clap command, arg={arg:?}, id={}", arg.id()?);
-            let mut clap_arg = Arg::new(arg.id()?);
-            if let Some(short) = arg.short() {
-                clap_arg = clap_arg.short(short);
+    fn build_clap_subcommand(&self, subcommand: &Subcommand) -> Result<Command> {
+        debug!(target: Self::LOG_TARGET, "build clap subcommand, subcommand={subcommand:?}");
+        let name = subcommand
+            .name()
+            .ok_or_else(|| self.locate(Error::MissingCommandName, subcommand.path()))?;
+        let mut command = Command::new(name.to_owned())
+            .visible_aliases(subcommand.aliases().into_iter().map(str::to_owned));
+        if let Some(about) = subcommand.about() {
+            command = command.about(about.to_owned());
+        }
+        self.build_clap_children(command, subcommand)
+    }
+
+    /// Add the arguments, groups and nested subcommands of the given
+    /// (sub)command definition to the clap command.
+    fn build_clap_children(&self, mut command: Command, def: &Subcommand) -> Result<Command> {
+        let args = def.args();
+        let ids = args.iter().map(|x| x.id()).collect::<Result<Vec<_>>>()?;
+        let check_reference = |key: &'static str, path: String, id: &str| {
+            if ids.iter().any(|x| x == id) {
+                Ok(())
+            } else {
+                let err = Error::UnknownReference {
+                    key,
+                    id: id.to_owned(),
+                };
+                Err(self.locate(err, &path))
             }
-            if let Some(long) = arg.long() {
-                clap_arg = clap_arg.long(long);
+        };
+
+        for arg in args.iter() {
+            for (i, id) in arg.conflicts_with().into_iter().enumerate() {
+                let path = format!("{}.conflicts_with[{i}]", arg.path());
+                check_reference("args[].conflicts_with", path, id)?;
             }
-            if arg.is_flag() {
-                clap_arg = clap_arg.action(clap::ArgAction::SetTrue);
+            for (i, id) in arg.requires().into_iter().enumerate() {
+                let path = format!("{}.requires[{i}]", arg.path());
+                check_reference("args[].requires", path, id)?;
             }
-            if let Some(help) = arg.help() {
-                clap_arg = clap_arg.help(help.to_string());
+            command = command.arg(self.build_clap_arg(arg)?);
+        }
+        for group in def.groups().iter() {
+            let name = group
+                .name()
+                .ok_or_else(|| self.locate(Error::MissingGroupName, group.path()))?;
+            for (i, id) in group.args().into_iter().enumerate() {
+                check_reference("groups[].args", format!("{}.args[{i}]", group.path()), id)?;
             }
-
-            command = command.arg(clap_arg);
+            command = command.group(
+                ArgGroup::new(name.to_owned())
+                    .args(group.args().into_iter().map(str::to_owned))
+                    .multiple(group.is_multiple())
+                    .required(group.is_required()),
+            );
+        }
+        match def.passthrough() {
+            Passthrough::None => {}
+            Passthrough::Trailing => {
+                command = command.arg(passthrough_arg().last(true));
+            }
+            Passthrough::Unknown => {
+                command = command.arg(
+                    passthrough_arg()
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true),
+                );
+            }
+        }
+        for subcommand in def.commands().iter() {
+            command = command.subcommand(self.build_clap_subcommand(subcommand)?);
+        }
+        if !def.commands().is_empty() {
+            command = command.subcommand_required(true);
         }
-        command.build();
         Ok(command)
     }
 
+    fn build_clap_arg(&self, arg: &Argument) -> Result<Arg> {
+        debug!(target: Self::LOG_TARGET, "build clap command, arg={arg:?}, id={}", arg.id()?);
+        let mut clap_arg = Arg::new(arg.id()?);
+        if let Some(short) = arg.short() {
+            clap_arg = clap_arg.short(short);
+        }
+        if let Some(long) = arg.long() {
+            clap_arg = clap_arg.long(long);
+        }
+        if arg.is_multiple() {
+            clap_arg = clap_arg
+                .action(clap::ArgAction::Append)
+                .default_values(arg.defaults().into_iter().map(str::to_owned));
+            if arg.is_positional() {
+                clap_arg = clap_arg.num_args(1..);
+            }
+        } else if let Some(default) = arg.default() {
+            clap_arg = clap_arg.default_value(default.to_owned());
+        }
+        let typ = arg.arg_type();
+        if typ == ArgType::Boolean {
+            clap_arg = clap_arg.action(clap::ArgAction::SetTrue);
+        } else if typ == ArgType::Count {
+            clap_arg = clap_arg.action(clap::ArgAction::Count);
+        } else if let Some(choices) = arg.select() {
+            clap_arg = clap_arg.value_parser(PossibleValuesParser::new(choices.to_vec()));
+        } else if typ.is_numeric() {
+            clap_arg = clap_arg
+                .value_parser(number_value_parser(typ, arg.min(), arg.max()))
+                .allow_negative_numbers(true);
+        } else {
+            // Accept any bytes, the value will be quoted on output.
+            clap_arg = clap_arg.value_parser(value_parser!(OsString));
+        }
+        if let Some(help) = arg.help() {
+            clap_arg = clap_arg.help(help.to_string());
+        }
+        if let Some(env) = self.env_name(arg)? {
+            clap_arg = clap_arg.env(env);
+        }
+        for id in arg.conflicts_with() {
+            clap_arg = clap_arg.conflicts_with(id.to_owned());
+        }
+        for id in arg.requires() {
+            clap_arg = clap_arg.requires(id.to_owned());
+        }
+        Ok(clap_arg.required(arg.is_required()))
+    }
+
     fn validate(&self) -> Result<()> {
         if self.parsed_version().is_none() {
-            return Err(Error::InvalidVersion);
+            return Err(self.locate(Error::InvalidVersion, "version"));
         }
         if self.program().is_empty() {
-            return Err(Error::MissingProgram);
+            return Err(self.locate(Error::MissingProgram, "program"));
+        }
+        let mut variables = HashMap::new();
+        if !self.commands().is_empty() {
+            variables.insert(self.command_variable_name(), "command".to_owned());
+        }
+        self.validate_arguments(&self.root())?;
+        self.validate_variable_names(&self.root(), variables, HashMap::new())
+    }
+
+    /// Make sure that the keys of every argument make sense together, and
+    /// that the arguments and subcommands of each (sub)command can be told
+    /// apart.
+    fn validate_arguments(&self, def: &Subcommand) -> Result<()> {
+        self.validate_argument_names(def)?;
+        self.validate_positionals(def)?;
+        self.validate_command_names(def)?;
+        for arg in def.args().iter() {
+            let typ = arg.arg_type();
+            if typ == ArgType::Count && arg.min().is_some() {
+                return Err(self.locate(Error::MinOnCount, &format!("{}.min", arg.path())));
+            }
+            if let Some(select) = arg.select() {
+                // The choices are matched as they are, so the range and the
+                // type of the argument would never be checked.
+                for (key, value) in [("min", arg.min()), ("max", arg.max())] {
+                    if value.is_some() {
+                        let path = format!("{}.{key}", arg.path());
+                        return Err(self.locate(Error::SelectWithRange { key }, &path));
+                    }
+                }
+                if typ.is_numeric() {
+                    let parse = number_value_parser(typ, None, None);
+                    if let Some(i) = select.iter().position(|x| parse(x).is_err()) {
+                        let err = Error::InvalidChoice {
+                            choice: select[i].clone(),
+                            typ,
+                        };
+                        return Err(self.locate(err, &format!("{}.select[{i}]", arg.path())));
+                    }
+                }
+            }
+        }
+        for subcommand in def.commands().iter() {
+            self.validate_arguments(subcommand)?;
+        }
+        Ok(())
+    }
+
+    /// Make sure that the ids, short and long names of the arguments are
+    /// unique within the (sub)command, and don't take the ones of the help.
+    fn validate_argument_names(&self, def: &Subcommand) -> Result<()> {
+        let mut ids = HashSet::new();
+        let mut shorts = HashMap::new();
+        let mut longs = HashMap::new();
+        for arg in def.args().iter() {
+            let id = arg.id().map_err(|err| self.locate(err, arg.path()))?;
+            let path = format!("{}.name", arg.path());
+            if id == "help" {
+                let err = Error::ReservedHelpName {
+                    name: id,
+                    key: "args[].name",
+                };
+                return Err(self.locate(err, &path));
+            }
+            if !ids.insert(id.clone()) {
+                return Err(self.locate(Error::DuplicateArgument { id }, &path));
+            }
+
+            let names = [
+                (arg.short().map(|x| format!("-{x}")), "short", &mut shorts),
+                (arg.long().map(|x| format!("--{x}")), "long", &mut longs),
+            ];
+            for (name, key, seen) in names {
+                let Some(name) = name else { continue };
+                let path = format!("{}.{key}", arg.path());
+                if name == "-h" || name == "--help" {
+                    let key = match key {
+                        "short" => "args[].short",
+                        _ => "args[].long",
+                    };
+                    return Err(self.locate(Error::ReservedHelpName { name, key }, &path));
+                }
+                if let Some(first) = seen.insert(name.clone(), id.clone()) {
+                    let err = Error::DuplicateOptionName {
+                        name,
+                        first,
+                        second: id,
+                    };
+                    return Err(self.locate(err, &path));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure that every positional argument can get a value: the optional
+    /// ones come after the required ones, and a variadic one is followed by a
+    /// single required one at most, ex. `[FILES..., DST]` as in `cp`.
+    fn validate_positionals(&self, def: &Subcommand) -> Result<()> {
+        let mut optional: Option<String> = None;
+        let mut variadic: Option<(String, usize)> = None;
+        for arg in def.args().iter().filter(|x| x.is_positional()) {
+            let id = arg.id().map_err(|err| self.locate(err, arg.path()))?;
+            if let Some((variadic, followers)) = &mut variadic {
+                if *followers > 0 || !arg.is_required() || arg.is_multiple() {
+                    let err = Error::PositionalAfterVariadic {
+                        id,
+                        variadic: variadic.clone(),
+                    };
+                    return Err(self.locate(err, arg.path()));
+                }
+                *followers += 1;
+            }
+            if arg.is_required() {
+                if let Some(optional) = &optional {
+                    let err = Error::OptionalBeforeRequired {
+                        optional: optional.clone(),
+                        required: id,
+                    };
+                    return Err(self.locate(err, arg.path()));
+                }
+            } else if optional.is_none() {
+                optional = Some(id.clone());
+            }
+            if arg.is_multiple() && variadic.is_none() {
+                variadic = Some((id, 0));
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure that the names and aliases of the subcommands are unique,
+    /// and don't take the one of the help subcommand.
+    fn validate_command_names(&self, def: &Subcommand) -> Result<()> {
+        let mut names = HashSet::new();
+        for subcommand in def.commands().iter() {
+            let path = format!("{}.name", subcommand.path());
+            for name in subcommand.name().into_iter().chain(subcommand.aliases()) {
+                if name == "help" {
+                    let err = Error::ReservedHelpName {
+                        name: name.to_owned(),
+                        key: "commands[].name",
+                    };
+                    return Err(self.locate(err, &path));
+                }
+                if !names.insert(name) {
+                    let err = Error::DuplicateCommandName {
+                        name: name.to_owned(),
+                    };
+                    return Err(self.locate(err, &path));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure that every argument maps to a valid and unique variable.
+    /// The variables of a subcommand are emitted together with the ones of
+    /// its ancestors, so they are checked against each other. The `arrays`
+    /// are the multiple arguments, which are emitted as `{name}_0`,
+    /// `{name}_1`, etc. in the indexed style. The style also depends on the
+    /// shell given when parsing, ex. `--shell sh`, so they are always
+    /// checked.
+    fn validate_variable_names(
+        &self,
+        def: &Subcommand,
+        mut variables: HashMap<String, String>,
+        mut arrays: HashMap<String, String>,
+    ) -> Result<()> {
+        for arg in def.args().iter() {
+            let id = arg.id().map_err(|err| self.locate(err, arg.path()))?;
+            let name = self.variable_name(arg)?;
+            // Points at the var of the argument, or the argument itself.
+            let path = format!("{}.var", arg.path());
+            if !shell::is_valid_name(&name) {
+                return Err(self.locate(Error::InvalidVariableName { id, name }, &path));
+            }
+            if shell::RESERVED_NAMES.contains(&name.as_str()) {
+                return Err(self.locate(Error::ReservedVariableName { id, name }, &path));
+            }
+
+            let mut names = vec![name.clone()];
+            if arg.is_multiple() {
+                names.push(format!("{name}_count"));
+            }
+            for name in names {
+                if let Some(first) = variables.insert(name.clone(), id.clone()) {
+                    let err = Error::DuplicateVariableName {
+                        name,
+                        first,
+                        second: id,
+                    };
+                    return Err(self.locate(err, &path));
+                }
+            }
+
+            // The elements of the indexed arrays are variables as well.
+            let element = match arrays.iter().find(|(x, _)| is_indexed_element(&name, x)) {
+                Some((_, first)) => Some((name.clone(), first.clone())),
+                None if arg.is_multiple() => variables
+                    .iter()
+                    .find(|(x, _)| is_indexed_element(x, &name))
+                    .map(|(x, first)| (x.clone(), first.clone())),
+                None => None,
+            };
+            if let Some((name, first)) = element {
+                let err = Error::DuplicateVariableName {
+                    name,
+                    first,
+                    second: id,
+                };
+                return Err(self.locate(err, &path));
+            }
+            if arg.is_multiple() {
+                arrays.insert(name, id);
+            }
+        }
+        for subcommand in def.commands().iter() {
+            self.validate_variable_names(subcommand, variables.clone(), arrays.clone())?;
         }
         Ok(())
     }
 }
 
+/// Represents a subcommand defined in the `commands` section, ex.
+///
+/// ```yaml
+/// commands:
+/// - name: apply
+///   about: Apply the changes.
+///   aliases: [up]
+///   args: [--force]
+/// ```
+#[derive(Debug, Clone)]
+pub struct Subcommand<'a> {
+    path: String,
+    name: Option<&'a str>,
+    about: Option<&'a str>,
+    aliases: &'a [String],
+    passthrough: Passthrough,
+    args: &'a [ArgSpec],
+    groups: &'a [GroupSpec],
+    commands: &'a [CommandSpec],
+}
+
+impl<'a> Subcommand<'a> {
+    pub fn new(spec: &'a CommandSpec) -> Self {
+        Self {
+            path: String::new(),
+            name: Some(spec.name.as_str()),
+            about: spec.about.as_deref(),
+            aliases: &spec.aliases,
+            passthrough: spec.passthrough,
+            args: &spec.args,
+            groups: &spec.groups,
+            commands: &spec.commands,
+        }
+    }
+
+    /// The top-level command of the spec, which has no name.
+    fn root(spec: &'a Spec) -> Self {
+        Self {
+            path: String::new(),
+            name: None,
+            about: spec.about.as_deref(),
+            aliases: &[],
+            passthrough: spec.passthrough,
+            args: &spec.args,
+            groups: &spec.groups,
+            commands: &spec.commands,
+        }
+    }
+
+    /// The path of the subcommand in the spec, ex. `commands[0]`, empty for
+    /// the top-level command.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The name of the subcommand, used in the command line and the output.
+    pub fn name(&self) -> Option<&'a str> {
+        self.name.filter(|x| !x.is_empty())
+    }
+
+    /// A description of the subcommand.
+    pub fn about(&self) -> Option<&'a str> {
+        self.about
+    }
+
+    /// The alternative names of the subcommand.
+    pub fn aliases(&self) -> Vec<&'a str> {
+        self.aliases.iter().map(String::as_str).collect()
+    }
+
+    /// The arguments of the subcommand.
+    pub fn args(&self) -> Vec<Argument<'a>> {
+        let path = join_path(&self.path, "args");
+        self.args
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| Argument {
+                path: format!("{path}[{i}]"),
+                ..Argument::new(spec)
+            })
+            .collect()
+    }
+
+    /// The nested subcommands of the subcommand.
+    pub fn commands(&self) -> Vec<Subcommand<'a>> {
+        let path = join_path(&self.path, "commands");
+        self.commands
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| Subcommand {
+                path: format!("{path}[{i}]"),
+                ..Subcommand::new(spec)
+            })
+            .collect()
+    }
+
+    /// Which arguments are passed through to the script (key: passthrough),
+    /// see [`Passthrough`]. Defaults to none.
+    pub fn passthrough(&self) -> Passthrough {
+        self.passthrough
+    }
+
+    /// The argument groups of the subcommand.
+    pub fn groups(&self) -> Vec<Group<'a>> {
+        let path = join_path(&self.path, "groups");
+        self.groups
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| Group {
+                path: format!("{path}[{i}]"),
+                ..Group::new(spec)
+            })
+            .collect()
+    }
+}
+
+/// Represents a [`clap::ArgGroup`], ex.
+///
+/// ```yaml
+/// groups:
+/// - name: mode
+///   args: [dry-run, force]
+///   multiple: false
+///   required: true
+/// ```
+#[derive(Debug, Clone)]
+pub struct Group<'a> {
+    spec: &'a GroupSpec,
+    path: String,
+}
+
+impl<'a> Group<'a> {
+    pub fn new(spec: &'a GroupSpec) -> Self {
+        Self {
+            spec,
+            path: String::new(),
+        }
+    }
+
+    /// The path of the group in the spec, ex. `groups[0]`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        Some(self.spec.name.as_str()).filter(|x| !x.is_empty())
+    }
+
+    /// The ids of the arguments in the group.
+    pub fn args(&self) -> Vec<&'a str> {
+        self.spec.args.iter().map(String::as_str).collect()
+    }
+
+    /// Whether more than one argument of the group can be present, false
+    /// by default.
+    pub fn is_multiple(&self) -> bool {
+        self.spec.multiple
+    }
+
+    /// Whether one of the arguments of the group must be present, false by
+    /// default.
+    pub fn is_required(&self) -> bool {
+        self.spec.required
+    }
+}
+
 /// Represents a [`clap::Arg`], see tutorial:
 /// https://docs.rs/clap/latest/clap/_tutorial/chapter_2/index.html
 #[derive(Debug, Clone)]
 pub struct Argument<'a> {
-    doc: &'a Yaml,
+    spec: &'a ArgSpec,
+    path: String,
 }
 
 impl<'a> Argument<'a> {
-    pub fn new(doc: &'a Yaml) -> Self {
-        Self { doc }
+    pub fn new(spec: &'a ArgSpec) -> Self {
+        Self {
+            spec,
+            path: String::new(),
+        }
+    }
+
+    /// The path of the argument in the spec, ex. `commands[0].args[1]`.
+    pub fn path(&self) -> &str {
+        &self.path
     }
 
     /// Only when an argument was defined using a single string, instead
@@ -147,8 +1033,8 @@ impl<'a> Argument<'a> {
     /// `args: [SRC, DST, -t/--threads, -s, --long]`, here every string
     /// in this array represents an argument. And the whole string of each
     /// is identified as a "bare name". "SRC" is a bare name, so as the rest.
-    pub fn bare_name(&self) -> Option<&str> {
-        self.doc.as_str()
+    pub fn bare_name(&self) -> Option<&'a str> {
+        self.spec.bare_name.as_deref()
     }
 
     /// The value of "name" in the provided arg definition.
@@ -160,31 +1046,37 @@ impl<'a> Argument<'a> {
     ///   short: -t
     ///   long: --threads
     /// ```
-    pub fn name(&self) -> Option<&str> {
-        self.doc["name"].as_str()
+    pub fn name(&self) -> Option<&'a str> {
+        self.spec.name.as_deref()
     }
 
     /// Provide the short arg name, ex. -c, -d, -t, etc.
     pub fn short(&self) -> Option<char> {
         let haystack = self
             .bare_name()
-            .or(self.doc["short"].as_str())
+            .or(self.spec.short.as_deref())
             .unwrap_or_default();
         extract_short_long_name(haystack)
             .0
-            .map(|x| x.chars().next())
-            .flatten()
+            .and_then(|x| x.chars().next())
     }
 
     /// Provide the long arg name, ex. --file, --num-threads, etc.
     pub fn long(&self) -> Option<String> {
         let haystack = self
             .bare_name()
-            .or(self.doc["long"].as_str())
+            .or(self.spec.long.as_deref())
             .unwrap_or_default();
         extract_short_long_name(haystack).1
     }
 
+    /// The name of a positional argument defined by a bare name, with the
+    /// brackets of an optional positional and the ellipsis of a variadic
+    /// positional stripped, ex. `SRC`, `[DST]`, `FILES...`, `[FILES...]`.
+    pub fn positional_name(&self) -> Option<&'a str> {
+        self.bare_name().map(|x| parse_positional_name(x).0)
+    }
+
     /// The id of the argument, it uses the value in the following order:
     /// name -> long -> short -> bare_name.
     pub fn id(&self) -> Result<String> {
@@ -192,69 +1084,457 @@ impl<'a> Argument<'a> {
             .map(|x| x.to_string())
             .or(self.long())
             .or(self.short().map(|x| x.to_string()))
-            .or(self.bare_name().map(|x| x.to_string()))
+            .or(self.positional_name().map(|x| x.to_string()))
             .ok_or(Error::MissingArgumentName)
     }
 
-    /// The type of the argument, can be string, number, boolean.
-    pub fn typ(&self) -> &str {
-        self.doc["type"].as_str().unwrap_or("string")
+    /// An argument without short and long names is a positional argument.
+    pub fn is_positional(&self) -> bool {
+        self.short().is_none() && self.long().is_none()
+    }
+
+    /// Whether the argument must be present (key: required). By default,
+    /// options are optional, while positional arguments are required unless
+    /// they have a default value or were defined as `[NAME]`.
+    pub fn is_required(&self) -> bool {
+        if let Some(required) = self.spec.required {
+            return required;
+        }
+        self.is_positional()
+            && self.spec.default.is_none()
+            && !self.bare_name().is_some_and(|x| parse_positional_name(x).1)
+    }
+
+    /// Whether the argument accepts multiple values (key: multiple), ex.
+    /// `-i a.txt -i b.txt` for options, or `FILES...` for positionals.
+    pub fn is_multiple(&self) -> bool {
+        if let Some(multiple) = self.spec.multiple {
+            return multiple;
+        }
+        self.bare_name().is_some_and(|x| parse_positional_name(x).2)
+    }
+
+    /// The type of the argument, see [`ArgType`] for all the supported types.
+    pub fn arg_type(&self) -> ArgType {
+        self.spec.typ
     }
 
     pub fn is_flag(&self) -> bool {
-        ["bool", "boolean"].contains(&self.typ())
+        self.arg_type() == ArgType::Boolean
+    }
+
+    /// The minimum value (inclusive) of a numeric argument.
+    pub fn min(&self) -> Option<f64> {
+        self.spec.min
+    }
+
+    /// The maximum value (inclusive) of a numeric argument, or the cap of a
+    /// counting flag.
+    pub fn max(&self) -> Option<f64> {
+        self.spec.max
+    }
+
+    /// The default value of the argument on absent, ex. `default: 8`.
+    pub fn default(&self) -> Option<&'a str> {
+        match &self.spec.default {
+            Some(DefaultValue::One(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The default values of a multi-valued argument, ex. `default: [a, b]`.
+    /// A single scalar is treated as a list of one value.
+    pub fn defaults(&self) -> Vec<&'a str> {
+        match &self.spec.default {
+            Some(DefaultValue::One(value)) => vec![value],
+            Some(DefaultValue::Many(values)) => values.iter().map(String::as_str).collect(),
+            None => vec![],
+        }
     }
 
-    /// The default value of the argument on absent.
-    pub fn default(&self) -> &str {
-        self.doc["default"].as_str().unwrap_or_default()
+    /// An explicit name of the variable in the output script (key: var),
+    /// which takes precedence over the naming strategy.
+    pub fn var(&self) -> Option<&'a str> {
+        self.spec.var.as_deref()
     }
 
-    pub fn help(&self) -> Option<&str> {
-        self.doc["help"].as_str()
+    /// The ids of the arguments which can't be used together with this one
+    /// (key: conflicts_with), ex. `conflicts_with: force` or `[a, b]`.
+    pub fn conflicts_with(&self) -> Vec<&'a str> {
+        self.spec
+            .conflicts_with
+            .iter()
+            .map(String::as_str)
+            .collect()
     }
 
-    pub fn select(&self) -> Option<Vec<&str>> {
-        self.doc["select"]
-            .as_vec()
-            .map(|x| x.iter().map(|v| v.as_str().unwrap_or_default()).collect())
+    /// The ids of the arguments which must be present when this one is
+    /// (key: requires), ex. `requires: token` or `[a, b]`.
+    pub fn requires(&self) -> Vec<&'a str> {
+        self.spec.requires.iter().map(String::as_str).collect()
     }
+
+    /// The environment variable to read the value from when the argument is
+    /// absent in the command line (key: env).
+    pub fn env(&self) -> Option<&'a str> {
+        self.spec.env.as_deref()
+    }
+
+    pub fn help(&self) -> Option<&'a str> {
+        self.spec.help.as_deref()
+    }
+
+    /// The closed set of values allowed for the argument, ex.
+    /// `select: [scp, rsync, aws]`.
+    pub fn select(&self) -> Option<&'a [String]> {
+        self.spec.select.as_deref()
+    }
+}
+
+/// The type of an argument (key: args[].type).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ArgType {
+    #[default]
+    String,
+    #[strum(to_string = "boolean", serialize = "bool")]
+    #[serde(alias = "bool")]
+    Boolean,
+    /// An integer or a decimal number.
+    Number,
+    /// A whole number, ex. `-3`, `0`, `42`.
+    Integer,
+    /// A decimal number, ex. `0.5`, `-1e3`, `42`.
+    Float,
+    /// The number of occurrences of a flag, ex. `-vvv` is 3.
+    Count,
 }
 
-pub fn parse(spec_yaml: &str, optstring: &[String]) -> Result<String> {
-    let mut docs = YamlLoader::load_from_str(spec_yaml)?;
-    validate_root_docs(&docs)?;
+impl ArgType {
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, ArgType::Number | ArgType::Integer | ArgType::Float)
+    }
+}
 
-    // Build an ArgumentParser instance by parsing the given spec.
-    let parser = ArgumentParser::new(docs.remove(0))?;
-    let command = parser.build_clap_command()?;
+/// How the ids of the arguments are converted to variable names (key: naming).
+/// Characters that are not allowed in shell variable names are replaced by
+/// underscores in all strategies, ex. `dry-run` becomes `dry_run`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, Deserialize)]
+pub enum NamingStrategy {
+    /// Keep the case of the id, ex. `SRC`, `dry_run`.
+    #[default]
+    #[strum(serialize = "keep")]
+    #[serde(rename = "keep")]
+    Keep,
+    /// Lower case, ex. `src`, `dry_run`.
+    #[strum(serialize = "snake_case")]
+    #[serde(rename = "snake_case")]
+    SnakeCase,
+    /// Upper case, ex. `SRC`, `DRY_RUN`.
+    #[strum(to_string = "SCREAMING_SNAKE", serialize = "screaming_snake")]
+    #[serde(rename = "SCREAMING_SNAKE", alias = "screaming_snake")]
+    ScreamingSnake,
+}
 
-    let optstring = normalize_optstring(optstring);
-    // Let the command parse optstring. And use the matches to compose the eval script.
-    debug!(target: "ramen::parse", "OPTSTRING: {optstring:?}");
-    let matches = command.get_matches_from(optstring);
-    compose_shell_script(&parser, &matches)
+impl NamingStrategy {
+    /// Convert the given argument id to a variable name.
+    pub fn apply(&self, id: &str) -> String {
+        let name: String = id
+            .chars()
+            .map(|x| match x {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => x,
+                _ => '_',
+            })
+            .collect();
+        match self {
+            NamingStrategy::Keep => name,
+            NamingStrategy::SnakeCase => name.to_ascii_lowercase(),
+            NamingStrategy::ScreamingSnake => name.to_ascii_uppercase(),
+        }
+    }
+}
+
+/// Which arguments are passed through to the script (key: passthrough). They
+/// are emitted as `set -- ...`, so that `"$@"` holds them after eval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Passthrough {
+    #[default]
+    None,
+    /// The arguments after `--`, ex. `wrapper -v -- --foo bar`.
+    Trailing,
+    /// The arguments from the first unrecognised one on, together with the
+    /// arguments after `--`, ex. `wrapper -v --foo bar`.
+    Unknown,
+}
+
+/// The argument collecting the arguments to pass through.
+fn passthrough_arg() -> Arg {
+    Arg::new(PASSTHROUGH_ARG_ID)
+        .value_name("ARGS")
+        .help("Arguments passed through to the script")
+        .num_args(0..)
+        .action(clap::ArgAction::Append)
+        .value_parser(value_parser!(OsString))
+}
+
+/// How multi-valued arguments are written in the output script (key: array_style).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ArrayStyle {
+    /// A shell array, ex. `FILES=('a' 'b')`.
+    #[default]
+    Native,
+    /// One variable per value, for shells without arrays, ex. `FILES_0='a'`
+    /// and `FILES_1='b'`.
+    Indexed,
+}
+
+/// Whether the variable is an element of the indexed array, ex. `FILES_0` of
+/// `FILES`.
+fn is_indexed_element(name: &str, array: &str) -> bool {
+    name.strip_prefix(array)
+        .and_then(|x| x.strip_prefix('_'))
+        .is_some_and(|x| !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Build a value parser which validates a numeric argument and its range.
+/// The value is kept as the string given by the user.
+pub(crate) fn number_value_parser(
+    typ: ArgType,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> impl Fn(&str) -> std::result::Result<String, String> + Clone + Send + Sync + 'static {
+    move |value: &str| {
+        let number = match typ {
+            ArgType::Integer => value.parse::<i64>().map(|x| x as f64).ok(),
+            _ => value.parse::<f64>().ok().filter(|x| x.is_finite()),
+        }
+        .ok_or_else(|| format!("{value:?} is not a valid {typ}"))?;
+
+        match (min, max) {
+            (Some(min), Some(max)) if number < min || number > max => {
+                Err(format!("{value} is not in {min}..={max}"))
+            }
+            (Some(min), None) if number < min => Err(format!("{value} is less than {min}")),
+            (None, Some(max)) if number > max => Err(format!("{value} is greater than {max}")),
+            _ => Ok(value.to_owned()),
+        }
+    }
+}
+
+/// The options of [`ArgumentParser::parse`], which are usually given in the
+/// command line of ramen, and take precedence over the spec.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// The dialect of the output script, overrides the `shell` key.
+    pub shell: Option<Shell>,
+
+    /// How the variables are declared, overrides the `declare` key.
+    pub declaration: Option<Declaration>,
+
+    /// The format of the output, a shell script by default.
+    pub format: OutputFormat,
+}
+
+/// The format of the output of [`ArgumentParser::parse`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A script to eval in the shell.
+    #[default]
+    Shell,
+    /// A JSON object, for non-shell consumers, ex. Python, jq or Makefiles.
+    Json,
+}
+
+/// Parse the optstring with the given spec (YAML, JSON or TOML, detected from
+/// the content), returns the script to eval in bash.
+pub fn parse<I, T>(spec: &str, optstring: I) -> Result<String>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    ArgumentParser::from_spec(spec, SpecFormat::Auto)?.parse(optstring, &ParseOptions::default())
 }
 
 /// Add some salts to the given optstring.
 /// Since we will be calling clap::Command::get_matches_from(VEC) API
 /// to parse the optstring, and it treats the first element from the given
 /// VEC as the name of the program, we insert a dummy value here to optstring.
-fn normalize_optstring(optstring: &[String]) -> Vec<String> {
-    let mut new_optstring = Vec::from(optstring);
-    if new_optstring.len() == 0 || new_optstring[0] != MAGIC_PROG_NAME {
-        new_optstring.insert(0, MAGIC_PROG_NAME.to_string());
+fn normalize_optstring<I, T>(optstring: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let mut new_optstring: Vec<OsString> = optstring.into_iter().map(Into::into).collect();
+    if new_optstring.is_empty() || new_optstring[0] != MAGIC_PROG_NAME {
+        new_optstring.insert(0, MAGIC_PROG_NAME.into());
     }
     new_optstring
 }
 
-fn compose_shell_script(parser: &ArgumentParser, matches: &ArgMatches) -> Result<String> {
+/// Walk down the chosen subcommands, ex. `deploy db migrate`, returns the
+/// (sub)command definitions along with their matches, from the root on.
+fn matched_commands<'p, 'm>(
+    parser: &'p ArgumentParser,
+    matches: &'m ArgMatches,
+) -> Vec<(Subcommand<'p>, &'m ArgMatches)> {
+    let mut levels = vec![(parser.root(), matches)];
+    loop {
+        let (def, matches) = &levels[levels.len() - 1];
+        let Some((name, sub_matches)) = matches.subcommand() else {
+            break;
+        };
+        let Some(subcommand) = def.commands().into_iter().find(|x| x.name() == Some(name)) else {
+            break;
+        };
+        levels.push((subcommand, sub_matches));
+    }
+    levels
+}
+
+/// The arguments to pass through, of the deepest (sub)command which enables
+/// passthrough, `None` if none of them does.
+fn passthrough_values<'m>(levels: &[(Subcommand, &'m ArgMatches)]) -> Option<Vec<&'m OsStr>> {
+    let mut passthrough = None;
+    for (def, matches) in levels {
+        if def.passthrough() != Passthrough::None {
+            let values = matches.get_raw(PASSTHROUGH_ARG_ID).into_iter().flatten();
+            passthrough = Some(values.collect());
+        }
+    }
+    passthrough
+}
+
+fn compose_shell_script(
+    parser: &ArgumentParser,
+    matches: &ArgMatches,
+    style: &ScriptStyle,
+) -> Result<String> {
     let mut script = String::with_capacity(256);
+    let shell = style.shell;
+
+    // Emit the arguments of each of the chosen subcommands, followed by the
+    // path of the subcommands.
+    let levels = matched_commands(parser, matches);
+    for (def, matches) in levels.iter() {
+        write_args(&mut script, parser, &def.args(), matches, style)?;
+    }
+    if !parser.commands().is_empty() {
+        let path: Vec<&str> = levels.iter().filter_map(|(def, _)| def.name()).collect();
+        let path = shell.quote(OsStr::new(&path.join(" ")));
+        let name = parser.command_variable_name();
+        writeln!(script, "{}", style.assign(VarKind::String, &name, &path))?;
+    }
+    // Replace the positional parameters of the calling script, so that "$@"
+    // holds the arguments passed through after eval.
+    if let Some(values) = passthrough_values(&levels) {
+        let quoted: Vec<String> = values.iter().map(|x| shell.quote(x)).collect();
+        writeln!(script, "{}", shell.set_positional(&quoted))?;
+    }
+
+    Ok(script)
+}
+
+/// Compose a JSON object of the parsed arguments, keyed by the argument ids,
+/// ex. `{"args": {"SRC": "a.txt"}, "subcommand": {"name": "apply", "args":
+/// {...}, "subcommand": null}, "passthrough": ["--foo"]}`. The subcommand and
+/// passthrough keys are only present when the spec defines them.
+fn compose_json(parser: &ArgumentParser, matches: &ArgMatches) -> Result<String> {
+    let levels = matched_commands(parser, matches);
+    let mut objects = Vec::with_capacity(levels.len());
+    for (def, matches) in levels.iter() {
+        let mut args = Map::new();
+        for arg in def.args().iter() {
+            args.insert(arg.id()?, json_value(arg, matches)?);
+        }
+        let mut object = Map::new();
+        if let Some(name) = def.name() {
+            object.insert("name".to_owned(), Value::from(name));
+        }
+        object.insert("args".to_owned(), Value::Object(args));
+        objects.push(object);
+    }
+
+    // Nest the subcommands into their parents, from the deepest one up.
+    let mut nested: Option<Value> = None;
+    for (index, mut object) in objects.into_iter().enumerate().rev() {
+        if !levels[index].0.commands().is_empty() {
+            object.insert(
+                "subcommand".to_owned(),
+                nested.take().unwrap_or(Value::Null),
+            );
+        }
+        nested = Some(Value::Object(object));
+    }
+    let mut root = match nested {
+        Some(Value::Object(root)) => root,
+        _ => Map::new(),
+    };
+    if let Some(values) = passthrough_values(&levels) {
+        let values = values.iter().map(|x| Value::from(x.to_string_lossy()));
+        root.insert("passthrough".to_owned(), Value::Array(values.collect()));
+    }
+    Ok(Value::Object(root).to_string())
+}
+
+/// Convert the value of the argument to JSON, according to its type. Absent
+/// arguments without a default value are null.
+fn json_value(arg: &Argument, matches: &ArgMatches) -> Result<Value> {
+    let key = arg.id()?;
+    let typ = arg.arg_type();
+    let to_json = |value: &OsStr| {
+        let value = value.to_string_lossy();
+        let number = match typ {
+            _ if !typ.is_numeric() => None,
+            ArgType::Float => value.parse::<f64>().ok().and_then(Number::from_f64),
+            _ => value
+                .parse::<i64>()
+                .map(Number::from)
+                .ok()
+                .or_else(|| value.parse::<f64>().ok().and_then(Number::from_f64)),
+        };
+        number.map(Value::Number).unwrap_or(Value::from(value))
+    };
+
+    Ok(if typ == ArgType::Boolean {
+        Value::Bool(matches.get_flag(&key))
+    } else if typ == ArgType::Count {
+        Value::from(count_value(arg, matches)?)
+    } else if arg.is_multiple() {
+        let values = matches.get_raw(&key).into_iter().flatten();
+        Value::Array(values.map(to_json).collect())
+    } else {
+        match matches.get_raw(&key).and_then(|mut values| values.next()) {
+            Some(value) => to_json(value),
+            None => Value::Null,
+        }
+    })
+}
+
+/// The number of occurrences of a counting flag, capped by the max value,
+/// ex. `max: 3` for `-vvvv`.
+fn count_value(arg: &Argument, matches: &ArgMatches) -> Result<u8> {
+    let count = matches.get_count(&arg.id()?);
+    Ok(match arg.max() {
+        Some(max) => count.min(max.max(0.0) as u8),
+        None => count,
+    })
+}
 
-    for arg in parser.args().iter() {
+fn write_args(
+    script: &mut String,
+    parser: &ArgumentParser,
+    args: &[Argument],
+    matches: &ArgMatches,
+    style: &ScriptStyle,
+) -> Result<()> {
+    for arg in args.iter() {
         let key = arg.id()?;
-        let prefix = parser.output_prefix();
-        let output_key = format!("{prefix}{key}");
+        let output_key = parser.variable_name(arg)?;
 
         debug!(
             target: "ramen::compose_shell_script",
@@ -262,27 +1542,118 @@ fn compose_shell_script(parser: &ArgumentParser, matches: &ArgMatches) -> Result
             matches.get_raw(&key),
         );
         if arg.is_flag() {
-            let flag = matches.get_flag(&key);
-            writeln!(&mut script, "{}={}", output_key, flag)?;
+            let flag = matches.get_flag(&key).to_string();
+            writeln!(
+                script,
+                "{}",
+                style.assign(VarKind::String, &output_key, &flag)
+            )?;
+        } else if arg.arg_type() == ArgType::Count {
+            let count = count_value(arg, matches)?.to_string();
+            writeln!(
+                script,
+                "{}",
+                style.assign(VarKind::Integer, &output_key, &count)
+            )?;
+        } else if arg.is_multiple() {
+            let values: Vec<OsString> = matches
+                .get_raw(&key)
+                .into_iter()
+                .flatten()
+                .map(|x| integer_value(arg, x).map_or_else(|| x.to_owned(), OsString::from))
+                .collect();
+            let values: Vec<&OsStr> = values.iter().map(OsString::as_os_str).collect();
+            write_array(script, style, &output_key, &values)?;
         } else {
-            let value = matches.get_one::<String>(&key);
-            if let Some(given_value) = value {
-                writeln!(&mut script, "{}={}", output_key, given_value)?;
+            // Always emit the variable, even if the argument is absent and has
+            // no default value, so that scripts running with `set -u` work.
+            let value = matches
+                .get_raw(&key)
+                .and_then(|mut values| values.next())
+                .unwrap_or_default();
+            // An absent integer is left empty rather than declared as 0.
+            let (kind, word) = match integer_value(arg, value) {
+                Some(integer) => (VarKind::Integer, style.shell.quote(OsStr::new(&integer))),
+                None => (VarKind::String, style.shell.quote(value)),
+            };
+            writeln!(script, "{}", style.assign(kind, &output_key, &word))?;
+        }
+    }
+    Ok(())
+}
+
+/// The value of an integer argument in plain decimal, ex. `8` for `08`, as
+/// `declare -i` and shell arithmetic read a leading zero as octal. The
+/// choices of a select are never parsed as numbers, so they are kept as is.
+fn integer_value(arg: &Argument, value: &OsStr) -> Option<String> {
+    match arg.select() {
+        None if arg.arg_type() == ArgType::Integer => value
+            .to_str()
+            .and_then(|x| x.parse::<i64>().ok())
+            .map(|x| x.to_string()),
+        _ => None,
+    }
+}
+
+/// Write a multi-valued argument as an array, followed by a variable holding
+/// the number of values, ex. `FILES=('a' 'b')` and `FILES_count=2`. The
+/// indexed style is used when the shell has no arrays.
+fn write_array(
+    script: &mut String,
+    style: &ScriptStyle,
+    output_key: &str,
+    values: &[&OsStr],
+) -> Result<()> {
+    let shell = style.shell;
+    let quoted: Vec<String> = values.iter().map(|x| shell.quote(x)).collect();
+    match shell.assign_array(style.declaration, output_key, &quoted) {
+        Some(statement) if style.array_style == ArrayStyle::Native => {
+            writeln!(script, "{statement}")?
+        }
+        _ => {
+            for (index, value) in quoted.iter().enumerate() {
+                let name = format!("{output_key}_{index}");
+                writeln!(script, "{}", style.assign(VarKind::String, &name, value))?;
             }
         }
     }
+    let count_key = format!("{output_key}_count");
+    let count = values.len().to_string();
+    writeln!(
+        script,
+        "{}",
+        style.assign(VarKind::Integer, &count_key, &count)
+    )?;
+    Ok(())
+}
 
-    Ok(script)
+/// How the variables are written in the output script.
+struct ScriptStyle {
+    shell: Shell,
+    declaration: Declaration,
+    array_style: ArrayStyle,
 }
 
-fn validate_root_docs(docs: &Vec<Yaml>) -> Result<()> {
-    if docs.len() == 0 {
-        return Err(Error::NoDocs);
+impl ScriptStyle {
+    fn assign(&self, kind: VarKind, name: &str, word: &str) -> String {
+        self.shell.assign(self.declaration, kind, name, word)
     }
-    if docs.len() > 1 {
-        return Err(Error::MultiDocs);
+}
+
+/// Parse the bare name of a positional argument, returns the name, whether
+/// it's optional (`[NAME]`) and whether it's variadic (`NAME...`).
+fn parse_positional_name(bare_name: &str) -> (&str, bool, bool) {
+    let (name, optional) = match bare_name
+        .strip_prefix('[')
+        .and_then(|x| x.strip_suffix(']'))
+    {
+        Some(name) => (name, true),
+        None => (bare_name, false),
+    };
+    match name.strip_suffix("...") {
+        Some(name) => (name, optional, true),
+        None => (name, optional, false),
     }
-    Ok(())
 }
 
 /// Extract the short and long name from the given text when it complies to the pattern `-s/--long`.
@@ -299,15 +1670,14 @@ fn extract_short_long_name(haystack: &str) -> (Option<String>, Option<String>) {
 
 #[cfg(test)]
 mod test {
-    use yaml_rust::{Yaml, YamlLoader};
-
     use crate::parser::Error;
+    use crate::spec::{ArgSpec, Spec, SpecFormat};
 
-    use super::{Argument, ArgumentParser};
+    use super::{ArgType, Argument, ArgumentParser, NamingStrategy};
 
     #[test]
     fn test_require_version_and_program_in_spec() -> anyhow::Result<()> {
-        let parser = ArgumentParser::new(load_yaml(
+        let parser = ArgumentParser::new(load_spec(
             r#"
         version: "1.0.0"
         program: hello
@@ -319,7 +1689,7 @@ mod test {
 
     #[test]
     fn test_err_missing_version() -> anyhow::Result<()> {
-        let parser_rs = ArgumentParser::new(load_yaml(
+        let parser_rs = ArgumentParser::new(load_spec(
             r#"
         program: hello
         "#,
@@ -331,7 +1701,7 @@ mod test {
 
     #[test]
     fn test_err_missing_program() -> anyhow::Result<()> {
-        let parser_rs = ArgumentParser::new(load_yaml(
+        let parser_rs = ArgumentParser::new(load_spec(
             r#"
         version: "1.0.0"
         "#,
@@ -343,8 +1713,8 @@ mod test {
 
     #[test]
     fn test_arg_bare_name() -> anyhow::Result<()> {
-        let doc = load_yaml("SRC")?;
-        let parg = Argument::new(&doc);
+        let spec = ArgSpec::from("SRC");
+        let parg = Argument::new(&spec);
         assert_eq!(Some("SRC"), parg.bare_name());
         assert_eq!("SRC", parg.id()?);
         Ok(())
@@ -352,20 +1722,557 @@ mod test {
 
     #[test]
     fn test_arg_name() -> anyhow::Result<()> {
-        let doc = load_yaml(
-            r#"
-        name: DEST
-        "#,
-        )?;
-        let parg = Argument::new(&doc);
+        let spec = ArgSpec {
+            name: Some("DEST".to_owned()),
+            ..Default::default()
+        };
+        let parg = Argument::new(&spec);
         assert!(parg.bare_name().is_none());
         assert_eq!("DEST", parg.id()?);
         Ok(())
     }
 
-    /// Helper function to load a YAML and returns the first doc.
-    fn load_yaml(yaml: &str) -> anyhow::Result<Yaml> {
-        let mut docs = YamlLoader::load_from_str(yaml)?;
-        Ok(docs.remove(0))
+    #[test]
+    fn test_select_choices() -> anyhow::Result<()> {
+        let parser = ArgumentParser::new(load_spec(
+            r#"
+        version: "1.0.0"
+        program: upload
+        args:
+          - name: protocol
+            long: --protocol
+            select: [scp, rsync, aws]
+        "#,
+        )?)?;
+        let mut command = parser.build_clap_command()?;
+
+        let matches = command
+            .clone()
+            .try_get_matches_from(["upload", "--protocol", "rsync"])?;
+        assert_eq!(
+            Some("rsync"),
+            matches.get_one::<String>("protocol").map(|x| x.as_str())
+        );
+
+        let err = command
+            .clone()
+            .try_get_matches_from(["upload", "--protocol", "ftp"])
+            .unwrap_err();
+        assert_eq!(clap::error::ErrorKind::InvalidValue, err.kind());
+        assert!(err
+            .to_string()
+            .contains("[possible values: scp, rsync, aws]"));
+
+        let help = command.render_help().to_string();
+        assert!(help.contains("[possible values: scp, rsync, aws]"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_err_select_choices() {
+        let new_parser = |arg: &str| {
+            ArgumentParser::new(
+                load_spec(&format!(
+                    "{{version: '1.0.0', program: upload, args: [{{name: threads, {arg}}}]}}"
+                ))
+                .unwrap(),
+            )
+        };
+
+        assert!(matches!(
+            new_parser("type: integer, select: ['1', '2'], max: 1"),
+            Err(Error::SelectWithRange { key: "max" })
+        ));
+        assert!(matches!(
+            new_parser("select: [a, b], min: 1"),
+            Err(Error::SelectWithRange { key: "min" })
+        ));
+        assert!(matches!(
+            new_parser("type: integer, select: ['1', 'a[$(id)]']"),
+            Err(Error::InvalidChoice { choice, typ: ArgType::Integer }) if choice == "a[$(id)]"
+        ));
+        assert!(matches!(
+            new_parser("type: float, select: ['0.5', 'inf']"),
+            Err(Error::InvalidChoice { choice, .. }) if choice == "inf"
+        ));
+        assert!(new_parser("type: number, select: ['0.5', '-1']").is_ok());
+    }
+
+    #[test]
+    fn test_err_argument_names() {
+        let new_parser = |spec: &str| {
+            ArgumentParser::new(
+                load_spec(&format!("{{version: '1.0.0', program: upload, {spec}}}")).unwrap(),
+            )
+        };
+
+        assert!(matches!(
+            new_parser("args: [--threads, {name: threads, short: -t}]"),
+            Err(Error::DuplicateArgument { id }) if id == "threads"
+        ));
+        assert!(matches!(
+            new_parser("args: [-t/--threads, {name: jobs, long: --threads}]"),
+            Err(Error::DuplicateOptionName { name, first, second })
+                if name == "--threads" && first == "threads" && second == "jobs"
+        ));
+        assert!(matches!(
+            new_parser("commands: [{name: a, args: [-t/--threads, -t/--tries]}]"),
+            Err(Error::DuplicateOptionName { name, .. }) if name == "-t"
+        ));
+        assert!(matches!(
+            new_parser("args: [-h/--host]"),
+            Err(Error::ReservedHelpName { name, .. }) if name == "-h"
+        ));
+        assert!(matches!(
+            new_parser("args: [{name: x, long: --help}]"),
+            Err(Error::ReservedHelpName { name, .. }) if name == "--help"
+        ));
+        assert!(matches!(
+            new_parser("args: [help]"),
+            Err(Error::ReservedHelpName { name, .. }) if name == "help"
+        ));
+        assert!(
+            new_parser("args: [-t/--threads], commands: [{name: a, args: [-t/--tries]}]").is_ok()
+        );
+    }
+
+    #[test]
+    fn test_err_positionals() {
+        let new_parser = |args: &str| {
+            ArgumentParser::new(
+                load_spec(&format!("{{version: '1.0.0', program: cp, args: {args}}}")).unwrap(),
+            )
+        };
+
+        assert!(matches!(
+            new_parser("['[A]', B]"),
+            Err(Error::OptionalBeforeRequired { optional, required }) if optional == "A" && required == "B"
+        ));
+        assert!(matches!(
+            new_parser("['[FILES...]', DST]"),
+            Err(Error::OptionalBeforeRequired { optional, .. }) if optional == "FILES"
+        ));
+        for args in [
+            "[FILES..., '[DST]']",
+            "[FILES..., DST, X]",
+            "[FILES..., SRCS...]",
+        ] {
+            assert!(
+                matches!(
+                    new_parser(args),
+                    Err(Error::PositionalAfterVariadic { variadic, .. }) if variadic == "FILES"
+                ),
+                "{args}"
+            );
+        }
+        // The last value goes to `DST`, as in `cp SRC... DST`.
+        assert!(new_parser("[FILES..., DST]").is_ok());
+        assert!(new_parser("[A, '[B]', '[C...]']").is_ok());
+    }
+
+    #[test]
+    fn test_err_command_names() {
+        let new_parser = |commands: &str| {
+            ArgumentParser::new(
+                load_spec(&format!(
+                    "{{version: '1.0.0', program: deploy, commands: {commands}}}"
+                ))
+                .unwrap(),
+            )
+        };
+
+        assert!(matches!(
+            new_parser("[{name: a}, {name: a}]"),
+            Err(Error::DuplicateCommandName { name }) if name == "a"
+        ));
+        assert!(matches!(
+            new_parser("[{name: a}, {name: b, commands: [{name: c, aliases: [c]}]}]"),
+            Err(Error::DuplicateCommandName { name }) if name == "c"
+        ));
+        assert!(matches!(
+            new_parser("[{name: help}]"),
+            Err(Error::ReservedHelpName { name, .. }) if name == "help"
+        ));
+        assert!(new_parser("[{name: a, commands: [{name: a}]}]").is_ok());
+    }
+
+    #[test]
+    fn test_err_min_on_count() -> anyhow::Result<()> {
+        let spec = |range: &str| {
+            load_spec(&format!(
+                "{{version: '1.0.0', program: x, args: [{{name: v, short: -v, type: count, {range}}}]}}"
+            ))
+        };
+        assert!(matches!(
+            ArgumentParser::new(spec("min: 1")?),
+            Err(Error::MinOnCount)
+        ));
+        assert!(ArgumentParser::new(spec("max: 3")?).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_types() -> anyhow::Result<()> {
+        let parser = ArgumentParser::new(load_spec(
+            r#"
+        version: "1.0.0"
+        program: upload
+        args:
+          - name: threads
+            long: --threads
+            type: integer
+            min: 1
+            max: 64
+          - name: ratio
+            long: --ratio
+            type: float
+            max: 1.0
+          - name: size
+            long: --size
+            type: number
+        "#,
+        )?)?;
+        let command = parser.build_clap_command()?;
+        let try_parse = |args: &[&str]| {
+            command
+                .clone()
+                .try_get_matches_from([&["upload"], args].concat())
+        };
+
+        let matches = try_parse(&["--threads", "8", "--ratio", "0.5", "--size", "-3"])?;
+        assert_eq!(
+            Some("8"),
+            matches.get_one::<String>("threads").map(|x| x.as_str())
+        );
+        assert_eq!(
+            Some("0.5"),
+            matches.get_one::<String>("ratio").map(|x| x.as_str())
+        );
+        assert_eq!(
+            Some("-3"),
+            matches.get_one::<String>("size").map(|x| x.as_str())
+        );
+
+        for invalid in [
+            vec!["--threads", "abc"],
+            vec!["--threads", "1.5"],
+            vec!["--threads", "0"],
+            vec!["--threads", "65"],
+            vec!["--ratio", "1.1"],
+            vec!["--ratio", "NaN"],
+            vec!["--size", "1e"],
+        ] {
+            let err = try_parse(&invalid).unwrap_err();
+            assert_eq!(
+                clap::error::ErrorKind::ValueValidation,
+                err.kind(),
+                "{invalid:?}"
+            );
+        }
+
+        let err = try_parse(&["--threads", "abc"]).unwrap_err().to_string();
+        assert!(err.contains("\"abc\" is not a valid integer"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_err_invalid_type() {
+        let err = load_spec(
+            r#"
+        version: "1.0.0"
+        program: upload
+        args:
+          - SRC
+          - name: threads
+            type: numbr
+        "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(Error::InvalidSpec { path, .. }) if path == "args[1].type"
+        ));
+        assert!(
+            err.to_string().starts_with("unknown variant `numbr`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_error_locations() {
+        let position = |spec: &str| {
+            let err = super::parse(spec, Vec::<String>::new()).unwrap_err();
+            err.location().map(|x| (x.line, x.column))
+        };
+
+        // Syntax errors.
+        assert_eq!(
+            Some((3, 8)),
+            position("version: \"1.0.0\"\nprogram: upload\nargs: [\"SRC]\n")
+        );
+        // Errors of the document model.
+        assert_eq!(
+            Some((5, 15)),
+            position(
+                "version: \"1.0.0\"\nprogram: upload\nargs:\n  - SRC\n  - {name: x, typo: 1}\n"
+            )
+        );
+        // Errors of the spec itself.
+        assert_eq!(Some((1, 10)), position("version: \"9\"\nprogram: upload\n"));
+        assert_eq!(
+            Some((5, 5)),
+            position("version: \"1.0.0\"\nprogram: upload\nargs:\n  - SRC\n  - help: no name\n")
+        );
+        assert_eq!(
+            Some((6, 36)),
+            position("version: \"1.0.0\"\nprogram: upload\ncommands:\n  - name: apply\n    args:\n      - {long: --force, requires: [dry-run]}\n")
+        );
+
+        // The snippet is rendered after the message.
+        let spec = "version: \"1.0.0\"\nprogram: upload\nargs:\n  - name: x\n    var: a b\n";
+        let err = ArgumentParser::from_yaml(spec).err().unwrap();
+        assert!(matches!(
+            err.without_location(),
+            Error::InvalidVariableName { .. }
+        ));
+        assert!(
+            err.to_string()
+                .ends_with(" --> line 5, column 10\n  |\n5 |     var: a b\n  |          ^^^"),
+            "{err}"
+        );
+
+        // There are no locations without the source of the spec.
+        let err = ArgumentParser::new(load_spec("version: \"9\"\nprogram: x").unwrap()).err();
+        assert!(matches!(err, Some(Error::InvalidVersion)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        const SPEC: &str = r#"
+        version: "1.0.0"
+        program: upload
+        args:
+          - name: threads
+            long: --threads
+            type: integer
+        "#;
+
+        let err = super::parse(SPEC, ["--help"]).unwrap_err();
+        assert!(matches!(err, Error::DisplayHelp { exit_code: 0, .. }));
+        assert!(!err.use_stderr());
+        assert!(err.to_string().starts_with("Usage: upload [OPTIONS]"));
+
+        let err = super::parse(SPEC, ["--unknown"]).unwrap_err();
+        assert!(matches!(err, Error::UnknownArgument { exit_code: 2, .. }));
+        assert!(err.use_stderr());
+        assert!(err.to_string().contains("unexpected argument '--unknown'"));
+
+        let err = super::parse(SPEC, ["--threads"]).unwrap_err();
+        assert!(matches!(err, Error::MissingValue { exit_code: 2, .. }));
+
+        let err = super::parse(SPEC, ["--threads", "abc"]).unwrap_err();
+        assert!(matches!(err, Error::InvalidValue { exit_code: 2, .. }));
+
+        let err = super::parse("version: \"1.0.0\"", ["--help"]).unwrap_err();
+        assert!(matches!(err.without_location(), Error::MissingProgram));
+        assert_eq!(None, err.exit_code());
+    }
+
+    #[test]
+    fn test_arg_optional_positional() -> anyhow::Result<()> {
+        assert!(Argument::new(&ArgSpec::from("SRC")).is_required());
+
+        let spec = ArgSpec::from("[DST]");
+        let parg = Argument::new(&spec);
+        assert_eq!("DST", parg.id()?);
+        assert!(parg.is_positional());
+        assert!(!parg.is_required());
+        Ok(())
+    }
+
+    #[test]
+    fn test_required_arguments() {
+        const SPEC: &str = r#"
+        version: "1.0.0"
+        program: upload
+        args:
+          - SRC
+          - "[DST]"
+          - name: token
+            long: --token
+            required: true
+        "#;
+
+        assert!(super::parse(SPEC, ["src", "--token", "x"]).is_ok());
+
+        let err = super::parse(SPEC, ["--token", "x"]).unwrap_err();
+        assert!(matches!(err, Error::MissingRequiredArgument { .. }));
+        assert!(err.to_string().contains("<SRC>"));
+
+        let err = super::parse(SPEC, ["src"]).unwrap_err();
+        assert!(matches!(err, Error::MissingRequiredArgument { .. }));
+        assert!(err.to_string().contains("--token <token>"));
+    }
+
+    #[test]
+    fn test_relationships() {
+        const SPEC: &str = r#"
+        version: "1.0.0"
+        program: deploy
+        args:
+          - name: dry-run
+            long: --dry-run
+            type: boolean
+            conflicts_with: force
+          - name: force
+            long: --force
+            type: boolean
+          - name: token
+            long: --token
+            requires: [user]
+          - name: user
+            long: --user
+          - name: json
+            long: --json
+            type: boolean
+          - name: yaml
+            long: --yaml
+            type: boolean
+        groups:
+          - name: format
+            args: [json, yaml]
+            required: true
+        "#;
+
+        assert!(super::parse(SPEC, ["--force", "--json"]).is_ok());
+        assert!(super::parse(SPEC, ["--token", "t", "--user", "u", "--yaml"]).is_ok());
+
+        let err = super::parse(SPEC, ["--dry-run", "--force", "--json"]).unwrap_err();
+        assert!(matches!(err, Error::ArgumentConflict { .. }));
+        assert!(err
+            .to_string()
+            .contains("the argument '--dry-run' cannot be used with '--force'"));
+
+        let err = super::parse(SPEC, ["--token", "t", "--json"]).unwrap_err();
+        assert!(matches!(err, Error::MissingRequiredArgument { .. }));
+        assert!(err.to_string().contains("--user <user>"));
+
+        let err = super::parse(SPEC, ["--json", "--yaml"]).unwrap_err();
+        assert!(matches!(err, Error::ArgumentConflict { .. }));
+
+        let err = super::parse(SPEC, ["--force"]).unwrap_err();
+        assert!(matches!(err, Error::MissingRequiredArgument { .. }));
+    }
+
+    #[test]
+    fn test_err_unknown_reference() -> anyhow::Result<()> {
+        let parser = ArgumentParser::new(load_spec(
+            r#"
+        version: "1.0.0"
+        program: deploy
+        args:
+          - name: dry-run
+            long: --dry-run
+            type: boolean
+            conflicts_with: forse
+        "#,
+        )?)?;
+        assert!(matches!(
+            parser.build_clap_command(),
+            Err(Error::UnknownReference { key: "args[].conflicts_with", id }) if id == "forse"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_naming_strategy() {
+        assert_eq!("dry_run", NamingStrategy::Keep.apply("dry-run"));
+        assert_eq!("SRC", NamingStrategy::Keep.apply("SRC"));
+        assert_eq!("src", NamingStrategy::SnakeCase.apply("SRC"));
+        assert_eq!("DRY_RUN", NamingStrategy::ScreamingSnake.apply("dry-run"));
+        assert_eq!(
+            NamingStrategy::ScreamingSnake,
+            NamingStrategy::try_from("SCREAMING_SNAKE").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_variable_names() -> anyhow::Result<()> {
+        let parser = ArgumentParser::new(load_spec(
+            r#"
+        version: "1.0.0"
+        program: deploy
+        output_prefix: opt_
+        naming: screaming_snake
+        args:
+          - --dry-run
+          - name: path
+            long: --path
+            var: target
+        "#,
+        )?)?;
+        let args = parser.args();
+        assert_eq!("opt_DRY_RUN", parser.variable_name(&args[0])?);
+        assert_eq!("opt_target", parser.variable_name(&args[1])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_err_variable_names() -> anyhow::Result<()> {
+        let new_parser = |args: &str| {
+            ArgumentParser::new(
+                load_spec(&format!(
+                    "{{version: '1.0.0', program: deploy, naming: screaming_snake, args: {args}}}"
+                ))
+                .unwrap(),
+            )
+        };
+
+        assert!(matches!(
+            new_parser("[--dry-run, DRY_RUN]"),
+            Err(Error::DuplicateVariableName { name, .. }) if name == "DRY_RUN"
+        ));
+        assert!(matches!(
+            new_parser("[--path]"),
+            Err(Error::ReservedVariableName { name, .. }) if name == "PATH"
+        ));
+        assert!(matches!(
+            new_parser("[{name: x, var: 'a b'}]"),
+            Err(Error::InvalidVariableName { name, .. }) if name == "a b"
+        ));
+        assert!(matches!(
+            new_parser("[FILES..., {name: x, var: FILES_count}]"),
+            Err(Error::DuplicateVariableName { name, .. }) if name == "FILES_count"
+        ));
+
+        // The elements of the indexed arrays.
+        let indexed = |args: &str| {
+            ArgumentParser::new(
+                load_spec(&format!(
+                    "{{version: '1.0.0', program: deploy, array_style: indexed, args: {args}}}"
+                ))
+                .unwrap(),
+            )
+        };
+        assert!(matches!(
+            indexed("[{name: ff, long: --ff, multiple: true}, --ff-0]"),
+            Err(Error::DuplicateVariableName { name, first, .. }) if name == "ff_0" && first == "ff"
+        ));
+        assert!(matches!(
+            indexed("[{name: x, var: FILES_12}, FILES...]"),
+            Err(Error::DuplicateVariableName { name, .. }) if name == "FILES_12"
+        ));
+        assert!(indexed("[FILES..., --files-x, {name: y, var: FILES_}]").is_ok());
+        // The shell without arrays may be given when parsing.
+        assert!(matches!(
+            new_parser("[FILES..., {name: x, long: --xx, var: FILES_0}]"),
+            Err(Error::DuplicateVariableName { name, .. }) if name == "FILES_0"
+        ));
+        Ok(())
+    }
+
+    /// Helper function to load a spec in YAML format.
+    fn load_spec(yaml: &str) -> anyhow::Result<Spec> {
+        Ok(SpecFormat::Yaml.load(yaml)?)
     }
 }
@@ -0,0 +1,183 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::parser::{Error, Result};
+
+/// The delimiter of the heredoc holding the spec, ex. `: <<'RAMEN'`.
+const HEREDOC_DELIMITER: &str = "RAMEN";
+
+/// The markers of the comment block holding the spec.
+const COMMENT_BEGIN: &str = "# ramen:begin";
+const COMMENT_END: &str = "# ramen:end";
+
+/// The start of the heredoc, the delimiter is captured as `unquoted` if it
+/// isn't quoted.
+static REG_HEREDOC_START: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<<(-?)\s*(?:'RAMEN'|"RAMEN"|\\RAMEN|(?P<unquoted>RAMEN))(\s|$)"#).unwrap()
+});
+
+/// Extract the spec embedded in a shell script, so that the script can parse
+/// its arguments with `ramen --from-script "$0" -- "$@"`. The spec is either
+/// in a heredoc delimited by `RAMEN`, ex.
+///
+/// ```bash
+/// : <<'RAMEN'
+/// version: "1.0.0"
+/// program: upload
+/// RAMEN
+/// ```
+///
+/// or in a comment block between `# ramen:begin` and `# ramen:end`, where
+/// the leading `# ` of each line is stripped. The first one found is used.
+///
+/// The delimiter of the heredoc must be quoted, the shell would expand the
+/// body of the heredoc otherwise, ex. `$(...)` in the spec would be run.
+pub fn extract_spec(script: &str) -> Result<Option<String>> {
+    let mut lines = script.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        if line.trim() == COMMENT_BEGIN {
+            return Ok(collect_until(
+                &mut lines,
+                |x| x.trim() == COMMENT_END,
+                |x| {
+                    let x = x.trim_start();
+                    x.strip_prefix("# ")
+                        .or_else(|| x.strip_prefix('#'))
+                        .unwrap_or(x)
+                },
+            ));
+        }
+        if let Some(caps) = REG_HEREDOC_START.captures(line) {
+            if caps.name("unquoted").is_some() {
+                return Err(Error::UnquotedHeredoc { line: index + 1 });
+            }
+            // The body of a `<<-` heredoc may be indented with tabs.
+            let strip_tabs = !caps[1].is_empty();
+            return Ok(collect_until(
+                &mut lines,
+                |x| {
+                    let x = if strip_tabs {
+                        x.trim_start_matches('\t')
+                    } else {
+                        x
+                    };
+                    x == HEREDOC_DELIMITER
+                },
+                |x| {
+                    if strip_tabs {
+                        x.trim_start_matches('\t')
+                    } else {
+                        x
+                    }
+                },
+            ));
+        }
+    }
+    Ok(None)
+}
+
+/// Collect the lines until the end marker, `None` if there isn't one.
+fn collect_until<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    is_end: impl Fn(&str) -> bool,
+    strip: impl Fn(&'a str) -> &'a str,
+) -> Option<String> {
+    let mut spec = String::new();
+    for (_, line) in lines {
+        if is_end(line) {
+            return Some(spec);
+        }
+        spec.push_str(strip(line));
+        spec.push('\n');
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::extract_spec;
+    use crate::parser::Error;
+
+    #[test]
+    fn test_extract_spec_from_heredoc() {
+        let script = r#"#!/usr/bin/env bash
+: <<'RAMEN'
+version: "1.0.0"
+program: upload
+args:
+  - SRC
+RAMEN
+
+eval "$(ramen --from-script "$0" -- "$@")"
+"#;
+        assert_eq!(
+            Some("version: \"1.0.0\"\nprogram: upload\nargs:\n  - SRC\n".to_owned()),
+            extract_spec(script).unwrap()
+        );
+
+        let script = "spec=$(cat <<-\"RAMEN\"\n\tprogram: upload\n\targs:\n\t  - SRC\n\tRAMEN\n)\n";
+        assert_eq!(
+            Some("program: upload\nargs:\n  - SRC\n".to_owned()),
+            extract_spec(script).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_extract_spec_from_comment() {
+        let script = r#"#!/usr/bin/env bash
+# ramen:begin
+# version: "1.0.0"
+# program: upload
+#
+# args:
+#   - SRC
+# ramen:end
+"#;
+        assert_eq!(
+            Some("version: \"1.0.0\"\nprogram: upload\n\nargs:\n  - SRC\n".to_owned()),
+            extract_spec(script).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_extract_spec_not_found() {
+        assert_eq!(
+            None,
+            extract_spec("#!/usr/bin/env bash\necho hi\n").unwrap()
+        );
+        assert_eq!(
+            None,
+            extract_spec(": <<'RAMEN'\nprogram: upload\n").unwrap()
+        );
+        assert_eq!(
+            None,
+            extract_spec("# ramen:begin\n# program: upload\n").unwrap()
+        );
+        assert_eq!(None, extract_spec("cat <<'RAMENS'\nRAMENS\n").unwrap());
+    }
+
+    #[test]
+    fn test_err_unquoted_heredoc() {
+        for script in [
+            "#!/bin/sh
+: <<RAMEN
+program: $(id)
+RAMEN
+",
+            "#!/bin/sh
+: <<- RAMEN
+	program: upload
+	RAMEN
+",
+        ] {
+            assert!(matches!(
+                extract_spec(script),
+                Err(Error::UnquotedHeredoc { line: 2 })
+            ));
+        }
+        assert_eq!(
+            Some("program: upload\n".to_owned()),
+            extract_spec(": <<\\RAMEN\nprogram: upload\nRAMEN\n").unwrap()
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::fmt::Write;
+use strum_macros::{Display, EnumString};
+
+/// Variables which have a special meaning to the shells, assigning to any of
+/// them would break the calling script in subtle ways.
+pub const RESERVED_NAMES: &[&str] = &[
+    // POSIX sh
+    "CDPATH",
+    "ENV",
+    "HOME",
+    "IFS",
+    "LANG",
+    "LC_ALL",
+    "LINENO",
+    "MAIL",
+    "MAILPATH",
+    "OLDPWD",
+    "OPTARG",
+    "OPTIND",
+    "PATH",
+    "PPID",
+    "PS1",
+    "PS2",
+    "PS4",
+    "PWD",
+    "SHELL",
+    "TERM",
+    "USER",
+    // bash
+    "BASH",
+    "BASHPID",
+    "BASH_ENV",
+    "BASH_SOURCE",
+    "EUID",
+    "FUNCNAME",
+    "GROUPS",
+    "HOSTNAME",
+    "PIPESTATUS",
+    "PS3",
+    "RANDOM",
+    "REPLY",
+    "SECONDS",
+    "SHELLOPTS",
+    "SHLVL",
+    "UID",
+    // zsh and fish
+    "argv",
+    "cdpath",
+    "fpath",
+    "path",
+    "pipestatus",
+    "status",
+];
+
+/// Whether the given name is a valid shell variable name, i.e. it consists
+/// of letters, digits and underscores, and doesn't start with a digit.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(x) if x.is_ascii_alphabetic() || x == '_')
+        && chars.all(|x| x.is_ascii_alphanumeric() || x == '_')
+}
+
+/// The dialect of the output script (key: shell).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, ValueEnum, Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    #[default]
+    Bash,
+    Zsh,
+    /// POSIX sh, ex. dash. Multi-valued arguments are always emitted in the
+    /// indexed style, since there are no arrays.
+    Sh,
+    Fish,
+}
+
+impl Shell {
+    /// Quote a value so that it can be safely embedded in a script of this
+    /// shell, see [`quote`].
+    pub fn quote(&self, value: &OsStr) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => quote(value),
+            Shell::Sh => quote_with(value, quote_utf8, quote_bytes_printf),
+            Shell::Fish => quote_with(value, quote_utf8_fish, quote_bytes_fish),
+        }
+    }
+
+    /// Whether the shell supports the given declaration modifier. There is
+    /// no readonly variable in fish, and no typed variable in POSIX sh.
+    pub fn supports(&self, declaration: Declaration) -> bool {
+        !matches!(
+            (self, declaration),
+            (Shell::Sh, Declaration::Typed)
+                | (Shell::Fish, Declaration::Readonly | Declaration::Typed)
+        )
+    }
+
+    /// A statement assigning the given (already quoted) word to a variable,
+    /// ex. `name=word`, `local name=word` or `set -l name word`.
+    pub fn assign(
+        &self,
+        declaration: Declaration,
+        kind: VarKind,
+        name: &str,
+        word: &str,
+    ) -> String {
+        match self {
+            Shell::Fish => format!("{}{name} {word}", self.keyword(declaration, kind)),
+            _ => format!("{}{name}={word}", self.keyword(declaration, kind)),
+        }
+    }
+
+    /// A statement assigning the given (already quoted) words to an array,
+    /// `None` if the shell has no arrays.
+    pub fn assign_array(
+        &self,
+        declaration: Declaration,
+        name: &str,
+        words: &[String],
+    ) -> Option<String> {
+        let keyword = self.keyword(declaration, VarKind::Array);
+        match self {
+            Shell::Bash | Shell::Zsh => Some(format!("{keyword}{name}=({})", words.join(" "))),
+            Shell::Sh => None,
+            Shell::Fish => Some(join_words(format!("{keyword}{name}"), words)),
+        }
+    }
+
+    /// The keyword (with a trailing space) which precedes an assignment.
+    fn keyword(&self, declaration: Declaration, kind: VarKind) -> &'static str {
+        match (self, declaration) {
+            (Shell::Fish, Declaration::Local) => "set -l ",
+            (Shell::Fish, Declaration::Export) => "set -gx ",
+            (Shell::Fish, _) => "set ",
+            (_, Declaration::None) => "",
+            (_, Declaration::Local) => "local ",
+            (_, Declaration::Export) => "export ",
+            (_, Declaration::Readonly) => "readonly ",
+            (_, Declaration::Typed) => match kind {
+                VarKind::String => "declare ",
+                VarKind::Integer => "declare -i ",
+                VarKind::Array => "declare -a ",
+            },
+        }
+    }
+
+    /// A statement replacing the positional parameters of the script with
+    /// the given (already quoted) words.
+    pub fn set_positional(&self, words: &[String]) -> String {
+        match self {
+            Shell::Fish => join_words("set argv".to_owned(), words),
+            _ => join_words("set --".to_owned(), words),
+        }
+    }
+
+    /// Compose a script which prints the given message to stdout (or stderr)
+    /// and then terminates the calling script with the given exit code. It's
+    /// used to forward the help, version and usage error messages to the
+    /// calling script, instead of printing them directly, where they would
+    /// be evaluated as code.
+    pub fn exit_script(&self, message: &str, use_stderr: bool, exit_code: i32) -> String {
+        let redirect = if use_stderr { " >&2" } else { "" };
+        format!(
+            "printf '%s'{redirect} {}\nexit {exit_code}\n",
+            self.quote(OsStr::new(message))
+        )
+    }
+}
+
+/// How the variables are declared in the output script (key: declare), ex.
+/// with `local` when eval runs inside a function.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, ValueEnum, Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Declaration {
+    /// Plain assignments, ex. `name='value'`.
+    #[default]
+    None,
+    /// `local name='value'`, or `set -l name 'value'` in fish.
+    Local,
+    /// `export name='value'`, or `set -gx name 'value'` in fish.
+    Export,
+    /// `readonly name='value'`.
+    Readonly,
+    /// `declare -i` for integers, `declare -a` for arrays, and `declare`
+    /// for the rest. Like `local`, `declare` is scoped to the function.
+    Typed,
+}
+
+/// The kind of the value of a variable, which is used by the typed
+/// declaration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarKind {
+    String,
+    Integer,
+    Array,
+}
+
+/// Quote a value so that it can be safely embedded in a shell script which
+/// will be evaluated by `eval`. The returned word always expands to exactly
+/// the bytes of the given value, no matter what it contains: whitespace,
+/// quotes, newlines, `$(...)`, globs or even invalid UTF-8 bytes.
+///
+/// Valid UTF-8 runs are wrapped in single quotes, where an embedded single
+/// quote is written as `'\''`. Bytes which are not valid UTF-8 are written
+/// with ANSI-C quoting (`$'\xff'`), which is understood by bash and zsh.
+pub fn quote(value: &OsStr) -> String {
+    quote_with(value, quote_utf8, quote_bytes)
+}
+
+/// Quote the valid UTF-8 runs and the invalid bytes of the value with the
+/// given functions, the quoted parts are concatenated into a single word.
+fn quote_with(
+    value: &OsStr,
+    quote_text: fn(&mut String, &str),
+    quote_invalid: fn(&mut String, &[u8]),
+) -> String {
+    let bytes = value.as_encoded_bytes();
+    if bytes.is_empty() {
+        return "''".to_string();
+    }
+
+    let mut quoted = String::with_capacity(bytes.len() + 2);
+    let mut invalid: Vec<u8> = Vec::new();
+    for chunk in bytes.utf8_chunks() {
+        if !chunk.valid().is_empty() {
+            if !invalid.is_empty() {
+                quote_invalid(&mut quoted, &invalid);
+                invalid.clear();
+            }
+            quote_text(&mut quoted, chunk.valid());
+        }
+        invalid.extend_from_slice(chunk.invalid());
+    }
+    if !invalid.is_empty() {
+        quote_invalid(&mut quoted, &invalid);
+    }
+    quoted
+}
+
+fn join_words(mut statement: String, words: &[String]) -> String {
+    for word in words {
+        statement.push(' ');
+        statement.push_str(word);
+    }
+    statement
+}
+
+fn quote_bytes(quoted: &mut String, bytes: &[u8]) {
+    quoted.push_str("$'");
+    for byte in bytes {
+        // Writing to a String never fails.
+        let _ = write!(quoted, "\\x{byte:02x}");
+    }
+    quoted.push('\'');
+}
+
+/// POSIX sh has no ANSI-C quoting, the bytes are produced by printf with
+/// octal escapes instead, ex. `"$(printf '\377')"`. The command substitution
+/// is safe here, since an invalid byte is never a trailing newline.
+fn quote_bytes_printf(quoted: &mut String, bytes: &[u8]) {
+    quoted.push_str("\"$(printf '");
+    for byte in bytes {
+        let _ = write!(quoted, "\\{byte:03o}");
+    }
+    quoted.push_str("')\"");
+}
+
+/// fish expands `\Xff` (outside of quotes) to the raw byte.
+fn quote_bytes_fish(quoted: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        let _ = write!(quoted, "\\X{byte:02x}");
+    }
+}
+
+fn quote_utf8(quoted: &mut String, text: &str) {
+    quoted.push('\'');
+    quoted.push_str(&text.replace('\'', r"'\''"));
+    quoted.push('\'');
+}
+
+/// In fish, backslashes and single quotes are escaped within single quotes.
+fn quote_utf8_fish(quoted: &mut String, text: &str) {
+    quoted.push('\'');
+    quoted.push_str(&text.replace('\\', r"\\").replace('\'', r"\'"));
+    quoted.push('\'');
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsStr;
+
+    use super::{is_valid_name, quote, Shell};
+
+    #[test]
+    fn test_quote() {
+        assert_eq!("''", quote(OsStr::new("")));
+        assert_eq!("'hello'", quote(OsStr::new("hello")));
+        assert_eq!("'a b'", quote(OsStr::new("a b")));
+        assert_eq!(r"'it'\''s'", quote(OsStr::new("it's")));
+        assert_eq!("'$(id)'", quote(OsStr::new("$(id)")));
+        assert_eq!("'a\nb'", quote(OsStr::new("a\nb")));
+    }
+
+    #[test]
+    fn test_quote_dialects() {
+        let value = OsStr::new(r"it's a \n");
+        assert_eq!(r"'it'\''s a \n'", Shell::Bash.quote(value));
+        assert_eq!(r"'it'\''s a \n'", Shell::Zsh.quote(value));
+        assert_eq!(r"'it'\''s a \n'", Shell::Sh.quote(value));
+        assert_eq!(r"'it\'s a \\n'", Shell::Fish.quote(value));
+        assert_eq!("''", Shell::Fish.quote(OsStr::new("")));
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("SRC"));
+        assert!(is_valid_name("_dry_run2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("dry-run"));
+        assert!(!is_valid_name("2fa"));
+        assert!(!is_valid_name("a b"));
+    }
+
+    #[test]
+    fn test_exit_script() {
+        assert_eq!(
+            "printf '%s' 'Usage: hello\n'\nexit 0\n",
+            Shell::Bash.exit_script("Usage: hello\n", false, 0)
+        );
+        assert_eq!(
+            "printf '%s' >&2 'error: it'\\''s wrong\n'\nexit 2\n",
+            Shell::Bash.exit_script("error: it's wrong\n", true, 2)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_quote_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(
+            r"'a'$'\xff\xfe''b'",
+            quote(OsStr::from_bytes(b"a\xff\xfeb"))
+        );
+        let value = OsStr::from_bytes(b"a\xff\xfeb");
+        assert_eq!(r#"'a'"$(printf '\377\376')"'b'"#, Shell::Sh.quote(value));
+        assert_eq!(r"'a'\Xff\Xfe'b'", Shell::Fish.quote(value));
+    }
+}
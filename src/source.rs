@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fmt;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+/// Where an error is in the spec document, rendered as a snippet of the line
+/// with a caret under the offending token, ex.
+///
+/// ```text
+///  --> line 7, column 11
+///   |
+/// 7 |     type: numbr
+///   |           ^^^^^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The line number, starting from 1.
+    pub line: usize,
+    /// The column number, starting from 1.
+    pub column: usize,
+    /// The text of the line.
+    text: String,
+    /// The width of the caret, in characters.
+    width: usize,
+}
+
+impl Location {
+    /// Create a location in the given source, `None` if the line doesn't
+    /// exist. The line and the column start from 1.
+    pub fn new(source: &str, line: usize, column: usize, width: usize) -> Option<Self> {
+        let text = source.lines().nth(line.checked_sub(1)?)?.to_owned();
+        Some(Self {
+            line,
+            column: column.max(1),
+            text,
+            width: width.max(1),
+        })
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f, "{gutter}--> line {}, column {}", self.line, self.column)?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", self.line, self.text)?;
+        write!(
+            f,
+            "{gutter} | {}{}",
+            " ".repeat(self.column - 1),
+            "^".repeat(self.width)
+        )
+    }
+}
+
+/// The locations of the keys and values of a YAML (or JSON) spec document,
+/// indexed by their paths, ex. `commands[0].args[1].type`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceMap {
+    source: String,
+    keys: HashMap<String, Span>,
+    values: HashMap<String, Span>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    line: usize,
+    column: usize,
+    width: usize,
+}
+
+impl SourceMap {
+    /// Index the given document, the map is empty if it isn't valid YAML.
+    pub(crate) fn new(source: &str) -> Self {
+        let mut collector = SpanCollector {
+            source,
+            map: SourceMap {
+                source: source.to_owned(),
+                ..Default::default()
+            },
+            stack: vec![],
+        };
+        match Parser::new(source.chars()).load(&mut collector, false) {
+            Ok(()) => collector.map,
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// The location of the value at the given path, or of its key if it has
+    /// no value. When neither exists, the nearest ancestor is used.
+    pub(crate) fn locate(&self, path: &str) -> Option<Location> {
+        self.find(path, |x| self.values.get(x).or(self.keys.get(x)))
+    }
+
+    /// The location of the key at the given path, ex. for unknown keys.
+    pub(crate) fn locate_key(&self, path: &str) -> Option<Location> {
+        self.find(path, |x| self.keys.get(x).or(self.values.get(x)))
+    }
+
+    fn find<'a>(&self, path: &str, get: impl Fn(&str) -> Option<&'a Span>) -> Option<Location>
+    where
+        Self: 'a,
+    {
+        let mut path = path;
+        loop {
+            if let Some(span) = get(path) {
+                return Location::new(&self.source, span.line, span.column, span.width);
+            }
+            if path.is_empty() {
+                return None;
+            }
+            path = parent_path(path);
+        }
+    }
+}
+
+/// The path of the parent, ex. `args[1]` for `args[1].type`, and `args`
+/// for `args[1]`. The root is an empty path.
+fn parent_path(path: &str) -> &str {
+    match path.rfind(['.', '[']) {
+        Some(index) => &path[..index],
+        None => "",
+    }
+}
+
+/// Join the path of a node and a key of it, ex. `commands[0].args`.
+pub(crate) fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+enum Frame {
+    Map { path: String, key: Option<String> },
+    Seq { path: String, index: usize },
+}
+
+struct SpanCollector<'a> {
+    source: &'a str,
+    map: SourceMap,
+    stack: Vec<Frame>,
+}
+
+impl SpanCollector<'_> {
+    /// The path of the next node.
+    fn node_path(&self) -> String {
+        match self.stack.last() {
+            None => String::new(),
+            Some(Frame::Map { path, key }) => join_path(path, key.as_deref().unwrap_or("?")),
+            Some(Frame::Seq { path, index }) => format!("{path}[{index}]"),
+        }
+    }
+
+    /// Move on to the next node of the parent when a node is complete.
+    fn complete(&mut self) {
+        match self.stack.last_mut() {
+            // Complex keys (maps or lists) aren't indexed.
+            Some(Frame::Map { key, .. }) if key.is_none() => *key = Some("?".to_owned()),
+            Some(Frame::Map { key, .. }) => *key = None,
+            Some(Frame::Seq { index, .. }) => *index += 1,
+            None => {}
+        }
+    }
+
+    /// The text of the line from the marker on.
+    fn rest_of_line(&self, mark: Marker) -> String {
+        let line = self.source.lines().nth(mark.line() - 1).unwrap_or_default();
+        line.chars().skip(mark.col()).collect()
+    }
+
+    fn span(&self, mark: Marker, value: &str) -> Span {
+        let rest = self.rest_of_line(mark);
+        // Cover the whole token, with its quotes if it's quoted.
+        let width = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].find(quote).map_or(1, |x| x + 2),
+            _ if !value.is_empty() && rest.starts_with(value) => value.chars().count(),
+            _ => 1,
+        };
+        Span {
+            line: mark.line(),
+            column: mark.col() + 1,
+            width,
+        }
+    }
+}
+
+impl MarkedEventReceiver for SpanCollector<'_> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, ..) => {
+                let span = self.span(mark, &value);
+                if let Some(Frame::Map {
+                    path,
+                    key: key @ None,
+                }) = self.stack.last_mut()
+                {
+                    // A block mapping starts at its first key.
+                    self.map.values.entry(path.clone()).or_insert(span);
+                    self.map.keys.insert(join_path(path, &value), span);
+                    *key = Some(value);
+                    return;
+                }
+                self.map.values.insert(self.node_path(), span);
+                self.complete();
+            }
+            Event::Alias(_) => {
+                let span = self.span(mark, "");
+                self.map.values.insert(self.node_path(), span);
+                self.complete();
+            }
+            Event::SequenceStart(_) | Event::MappingStart(_) => {
+                let path = self.node_path();
+                // The marker of a block mapping is at the colon of its first
+                // key, so it's located by the key instead.
+                let block_mapping = matches!(event, Event::MappingStart(_))
+                    && !self.rest_of_line(mark).starts_with('{');
+                if !block_mapping {
+                    self.map.values.insert(path.clone(), self.span(mark, ""));
+                }
+                self.stack.push(match event {
+                    Event::SequenceStart(_) => Frame::Seq { path, index: 0 },
+                    _ => Frame::Map { path, key: None },
+                });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.stack.pop();
+                self.complete();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SourceMap;
+
+    const SPEC: &str = r#"version: "1.0.0"
+program: upload
+args:
+  - SRC
+  - name: threads
+    type: numbr
+    conflicts_with: [SRC, dst]
+commands:
+  - {name: apply, args: [--force]}
+"#;
+
+    #[test]
+    fn test_locate() {
+        let map = SourceMap::new(SPEC);
+        let position = |path: &str| map.locate(path).map(|x| (x.line, x.column));
+
+        assert_eq!(Some((1, 10)), position("version"));
+        assert_eq!(Some((4, 5)), position("args[0]"));
+        assert_eq!(Some((6, 11)), position("args[1].type"));
+        assert_eq!(Some((7, 27)), position("args[1].conflicts_with[1]"));
+        assert_eq!(Some((9, 26)), position("commands[0].args[0]"));
+        // Falls back to the nearest ancestor.
+        assert_eq!(Some((6, 11)), position("args[1].type.x"));
+        assert_eq!(Some((5, 5)), position("args[1].env"));
+        assert_eq!(
+            Some((6, 5)),
+            map.locate_key("args[1].type").map(|x| (x.line, x.column))
+        );
+    }
+
+    #[test]
+    fn test_location_snippet() {
+        let map = SourceMap::new(SPEC);
+        assert_eq!(
+            " --> line 6, column 11\n  |\n6 |     type: numbr\n  |           ^^^^^",
+            map.locate("args[1].type").unwrap().to_string()
+        );
+        assert_eq!(
+            " --> line 1, column 10\n  |\n1 | version: \"1.0.0\"\n  |          ^^^^^^^",
+            map.locate("version").unwrap().to_string()
+        );
+    }
+}
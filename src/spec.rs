@@ -0,0 +1,637 @@
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+use std::fmt;
+use std::path::Path;
+use strum_macros::{Display, EnumString};
+use yaml_rust::yaml::Hash;
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::parser::{ArgType, ArrayStyle, Error, NamingStrategy, Passthrough, Result};
+use crate::shell::{Declaration, Shell};
+
+/// The first line of a TOML document, a table header or a `key = value`.
+static REG_TOML_LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^(\[.*\]|[A-Za-z0-9_."'-]+\s*=)"#).unwrap());
+
+/// The spec of an argument parser, i.e. the root of a spec document, ex.
+///
+/// ```yaml
+/// version: "1.0.0"
+/// program: upload
+/// args: [SRC, -v/--verbose]
+/// ```
+///
+/// Unknown keys and values of the wrong type are rejected on loading. Specs
+/// can also be built in Rust, ex.
+///
+/// ```
+/// use ramen::spec::{ArgSpec, Spec};
+///
+/// let spec = Spec {
+///     version: "1.0.0".to_owned(),
+///     program: "upload".to_owned(),
+///     args: vec![ArgSpec::from("SRC"), ArgSpec::from("-v/--verbose")],
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Spec {
+    pub version: String,
+    pub program: String,
+    pub about: Option<String>,
+    /// A prefix of the variables in the output script.
+    pub output_prefix: String,
+    pub array_style: ArrayStyle,
+    pub naming: NamingStrategy,
+    pub shell: Shell,
+    pub declare: Declaration,
+    /// A prefix to derive the environment variables of the arguments.
+    pub env_prefix: Option<String>,
+    pub passthrough: Passthrough,
+    #[serde(deserialize_with = "arg_list")]
+    pub args: Vec<ArgSpec>,
+    pub groups: Vec<GroupSpec>,
+    pub commands: Vec<CommandSpec>,
+}
+
+/// A subcommand defined in the `commands` section.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandSpec {
+    pub name: String,
+    pub about: Option<String>,
+    pub aliases: Vec<String>,
+    pub passthrough: Passthrough,
+    #[serde(deserialize_with = "arg_list")]
+    pub args: Vec<ArgSpec>,
+    pub groups: Vec<GroupSpec>,
+    pub commands: Vec<CommandSpec>,
+}
+
+/// An argument group defined in the `groups` section.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupSpec {
+    pub name: String,
+    #[serde(deserialize_with = "string_list")]
+    pub args: Vec<String>,
+    pub multiple: bool,
+    pub required: bool,
+}
+
+/// An argument defined in the `args` section, either by a bare name, ex.
+/// `SRC` or `-t/--threads`, or by a map of its keys.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArgSpec {
+    /// The whole string of an argument defined by a bare name.
+    #[serde(skip)]
+    pub bare_name: Option<String>,
+    pub name: Option<String>,
+    pub short: Option<String>,
+    pub long: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: ArgType,
+    pub multiple: Option<bool>,
+    pub required: Option<bool>,
+    pub default: Option<DefaultValue>,
+    pub var: Option<String>,
+    #[serde(deserialize_with = "string_list")]
+    pub conflicts_with: Vec<String>,
+    #[serde(deserialize_with = "string_list")]
+    pub requires: Vec<String>,
+    pub env: Option<String>,
+    pub help: Option<String>,
+    #[serde(deserialize_with = "scalar_list")]
+    pub select: Option<Vec<String>>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl From<&str> for ArgSpec {
+    /// An argument defined by a bare name.
+    fn from(bare_name: &str) -> Self {
+        Self {
+            bare_name: Some(bare_name.to_owned()),
+            ..Default::default()
+        }
+    }
+}
+
+/// The default value of an argument, a single value, or a list of values
+/// of a multi-valued argument. Scalars of any type are accepted, ex.
+/// `default: 8`, `default: true`, `default: [a, b]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefaultValue {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl<'de> Deserialize<'de> for DefaultValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct DefaultVisitor;
+
+        impl<'de> Visitor<'de> for DefaultVisitor {
+            type Value = DefaultValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a scalar or a list of scalars")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<Self::Value, E> {
+                Ok(DefaultValue::One(v.to_string()))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Self::Value, E> {
+                Ok(DefaultValue::One(v.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Self::Value, E> {
+                Ok(DefaultValue::One(v.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<Self::Value, E> {
+                Ok(DefaultValue::One(v.to_string()))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Self::Value, E> {
+                Ok(DefaultValue::One(v.to_owned()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let mut values = Vec::new();
+                while let Some(Scalar(value)) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(DefaultValue::Many(values))
+            }
+        }
+
+        deserializer.deserialize_any(DefaultVisitor)
+    }
+}
+
+/// A scalar of any type, kept in its string form.
+struct Scalar(String);
+
+impl<'de> Deserialize<'de> for Scalar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match DefaultValue::deserialize(deserializer)? {
+            DefaultValue::One(value) => Ok(Scalar(value)),
+            DefaultValue::Many(_) => Err(de::Error::invalid_type(
+                de::Unexpected::Seq,
+                &"a string, a number or a boolean",
+            )),
+        }
+    }
+}
+
+/// Deserialize a list of arguments, each is a bare name or a map.
+fn arg_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<ArgSpec>, D::Error> {
+    struct Entry(ArgSpec);
+
+    impl<'de> Deserialize<'de> for Entry {
+        fn deserialize<D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            struct EntryVisitor;
+
+            impl<'de> Visitor<'de> for EntryVisitor {
+                type Value = Entry;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("a bare name or a map of the argument")
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Self::Value, E> {
+                    Ok(Entry(ArgSpec::from(v)))
+                }
+
+                fn visit_map<A: MapAccess<'de>>(
+                    self,
+                    map: A,
+                ) -> std::result::Result<Self::Value, A::Error> {
+                    ArgSpec::deserialize(de::value::MapAccessDeserializer::new(map)).map(Entry)
+                }
+            }
+
+            deserializer.deserialize_any(EntryVisitor)
+        }
+    }
+
+    let entries = Vec::<Entry>::deserialize(deserializer)?;
+    Ok(entries.into_iter().map(|x| x.0).collect())
+}
+
+/// Deserialize a list of strings, a single string is treated as a list of
+/// one, ex. `requires: token` or `requires: [user, token]`.
+fn string_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<String>, D::Error> {
+    struct StringListVisitor;
+
+    impl<'de> Visitor<'de> for StringListVisitor {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a string or a list of strings")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Self::Value, E> {
+            Ok(vec![v.to_owned()])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> std::result::Result<Self::Value, A::Error> {
+            let mut values = Vec::new();
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(values)
+        }
+    }
+
+    deserializer.deserialize_any(StringListVisitor)
+}
+
+/// Deserialize a list of scalars, ex. `select: [1, 2, 4]`.
+fn scalar_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<String>>, D::Error> {
+    let values = Option::<Vec<Scalar>>::deserialize(deserializer)?;
+    Ok(values.map(|x| x.into_iter().map(|x| x.0).collect()))
+}
+
+/// The format of a spec document. Whatever the format, the spec is loaded
+/// into the same document model, so the keys are the same in all formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, ValueEnum)]
+#[strum(serialize_all = "lowercase")]
+pub enum SpecFormat {
+    /// Detect the format from the content, see [`SpecFormat::detect`].
+    #[default]
+    Auto,
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl SpecFormat {
+    /// The format of a spec file by its extension, `None` if unknown.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(SpecFormat::Yaml),
+            "json" => Some(SpecFormat::Json),
+            "toml" => Some(SpecFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Detect the format of a spec document: JSON if it starts with `{`,
+    /// TOML if it starts with a table header or a `key = value` line,
+    /// otherwise YAML. Blank lines and comments are skipped. A YAML flow
+    /// mapping starts with `{` as well, ex. `{program: x}`, so such a
+    /// document is loaded as YAML if it isn't valid JSON.
+    pub fn detect(spec: &str) -> Self {
+        let first_line = spec
+            .lines()
+            .map(str::trim)
+            .find(|x| !x.is_empty() && !x.starts_with('#'))
+            .unwrap_or_default();
+        if first_line.starts_with('{') {
+            SpecFormat::Json
+        } else if REG_TOML_LINE.is_match(first_line) {
+            SpecFormat::Toml
+        } else {
+            SpecFormat::Yaml
+        }
+    }
+
+    /// The format of the given spec document, detected if it's auto.
+    pub fn resolve(&self, spec: &str) -> Self {
+        match self {
+            SpecFormat::Auto => SpecFormat::detect(spec),
+            format => *format,
+        }
+    }
+
+    /// Load a spec document of this format.
+    pub fn load(&self, spec: &str) -> Result<Spec> {
+        Spec::from_doc(&self.load_doc(spec)?)
+    }
+
+    /// Load a spec document of this format into the document model.
+    fn load_doc(&self, spec: &str) -> Result<Yaml> {
+        match self {
+            SpecFormat::Auto => match SpecFormat::detect(spec) {
+                SpecFormat::Json => SpecFormat::Json
+                    .load_doc(spec)
+                    .or_else(|err| SpecFormat::Yaml.load_doc(spec).map_err(|_| err)),
+                format => format.load_doc(spec),
+            },
+            SpecFormat::Yaml => {
+                let mut docs = YamlLoader::load_from_str(spec)?;
+                if docs.is_empty() {
+                    return Err(Error::NoDocs);
+                }
+                if docs.len() > 1 {
+                    return Err(Error::MultiDocs);
+                }
+                Ok(docs.remove(0))
+            }
+            SpecFormat::Json => Ok(json_to_yaml(serde_json::from_str(spec)?)),
+            SpecFormat::Toml => Ok(toml_to_yaml(toml::from_str(spec)?)),
+        }
+    }
+}
+
+impl Spec {
+    /// Deserialize the spec from a document, the errors are reported along
+    /// with the path of the key, ex. `args[1].type`.
+    pub fn from_doc(doc: &Yaml) -> Result<Self> {
+        serde_path_to_error::deserialize(YamlDeserializer(doc)).map_err(|err| Error::InvalidSpec {
+            path: err.path().to_string(),
+            message: err.inner().to_string(),
+        })
+    }
+}
+
+/// A [`Deserializer`] of the document model.
+struct YamlDeserializer<'a>(&'a Yaml);
+
+impl<'de> Deserializer<'de> for YamlDeserializer<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.0 {
+            Yaml::Null | Yaml::BadValue => visitor.visit_unit(),
+            Yaml::Boolean(x) => visitor.visit_bool(*x),
+            Yaml::Integer(x) => visitor.visit_i64(*x),
+            // Reals are kept in their string form, ex. `default: 1.0`, unless
+            // a number is expected, see `deserialize_f64`.
+            Yaml::Real(x) | Yaml::String(x) => visitor.visit_str(x),
+            Yaml::Array(x) => {
+                visitor.visit_seq(SeqDeserializer::new(x.iter().map(YamlDeserializer)))
+            }
+            Yaml::Hash(x) => visitor.visit_map(MapDeserializer::new(
+                x.iter()
+                    .map(|(k, v)| (YamlDeserializer(k), YamlDeserializer(v))),
+            )),
+            Yaml::Alias(_) => Err(de::Error::custom("aliases are not supported")),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.0 {
+            Yaml::Real(x) => match x.parse() {
+                Ok(number) => visitor.visit_f64(number),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(x), &visitor)),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.0 {
+            Yaml::Null | Yaml::BadValue => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.0 {
+            Yaml::String(x) => visitor.visit_enum(x.as_str().into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, de::value::Error> for YamlDeserializer<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn json_to_yaml(value: serde_json::Value) -> Yaml {
+    use serde_json::Value;
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(x) => Yaml::Boolean(x),
+        Value::Number(x) => match x.as_i64() {
+            Some(x) => Yaml::Integer(x),
+            None => Yaml::Real(x.to_string()),
+        },
+        Value::String(x) => Yaml::String(x),
+        Value::Array(x) => Yaml::Array(x.into_iter().map(json_to_yaml).collect()),
+        Value::Object(x) => Yaml::Hash(
+            x.into_iter()
+                .map(|(k, v)| (Yaml::String(k), json_to_yaml(v)))
+                .collect::<Hash>(),
+        ),
+    }
+}
+
+fn toml_to_yaml(value: toml::Value) -> Yaml {
+    use toml::Value;
+    match value {
+        Value::Boolean(x) => Yaml::Boolean(x),
+        Value::Integer(x) => Yaml::Integer(x),
+        // The debug form keeps the fraction of whole numbers, ex. `1.0`
+        // rather than `1`, the same as the source text in YAML and JSON.
+        Value::Float(x) => Yaml::Real(format!("{x:?}")),
+        Value::String(x) => Yaml::String(x),
+        Value::Datetime(x) => Yaml::String(x.to_string()),
+        Value::Array(x) => Yaml::Array(x.into_iter().map(toml_to_yaml).collect()),
+        Value::Table(x) => Yaml::Hash(
+            x.into_iter()
+                .map(|(k, v)| (Yaml::String(k), toml_to_yaml(v)))
+                .collect::<Hash>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArgSpec, DefaultValue, SpecFormat};
+    use crate::parser::Error;
+
+    const YAML: &str = r#"
+# The spec of upload.
+version: "1.0.0"
+program: upload
+args:
+  - SRC
+  - name: threads
+    long: --threads
+    default: 4
+    min: 0.5
+  - name: ratio
+    long: --ratio
+    type: float
+    default: 1.0
+    select: [1.0, 2.5]
+"#;
+
+    const JSON: &str = r#"
+{
+  "version": "1.0.0",
+  "program": "upload",
+  "args": [
+    "SRC",
+    {"name": "threads", "long": "--threads", "default": 4, "min": 0.5},
+    {"name": "ratio", "long": "--ratio", "type": "float", "default": 1.0, "select": [1.0, 2.5]}
+  ]
+}
+"#;
+
+    const TOML: &str = r#"
+# The spec of upload.
+version = "1.0.0"
+program = "upload"
+args = [
+  "SRC",
+  { name = "threads", long = "--threads", default = 4, min = 0.5 },
+  { name = "ratio", long = "--ratio", type = "float", default = 1.0, select = [1.0, 2.5] },
+]
+"#;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(SpecFormat::Yaml, SpecFormat::detect(YAML));
+        assert_eq!(SpecFormat::Json, SpecFormat::detect(JSON));
+        assert_eq!(SpecFormat::Toml, SpecFormat::detect(TOML));
+        assert_eq!(
+            SpecFormat::Toml,
+            SpecFormat::detect("[[args]]\nname = \"SRC\"")
+        );
+        assert_eq!(SpecFormat::Yaml, SpecFormat::detect("program: a=b"));
+    }
+
+    #[test]
+    fn test_load_same_model() -> anyhow::Result<()> {
+        let yaml = SpecFormat::Yaml.load(YAML)?;
+        assert_eq!(yaml, SpecFormat::Json.load(JSON)?);
+        assert_eq!(yaml, SpecFormat::Toml.load(TOML)?);
+        assert_eq!(yaml, SpecFormat::Auto.load(TOML)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_yaml_flow_mapping() -> anyhow::Result<()> {
+        let spec = "{version: \"1.0.0\", program: upload, args: [SRC]}";
+        let loaded = SpecFormat::Auto.load(spec)?;
+        assert_eq!("upload", loaded.program);
+        assert_eq!(vec![ArgSpec::from("SRC")], loaded.args);
+
+        // Invalid in both formats, the error of JSON is reported.
+        assert!(matches!(
+            SpecFormat::Auto.load("{\"version\": "),
+            Err(Error::ParseJson(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_unknown_keys_and_wrong_types() {
+        let invalid_spec = |yaml: &str| match SpecFormat::Yaml.load(yaml) {
+            Err(Error::InvalidSpec { path, message }) => (path, message),
+            other => panic!("unexpected result: {other:?}"),
+        };
+
+        let (path, message) = invalid_spec("program: upload\nargs: [{name: x, defualt: 4}]");
+        assert_eq!("args[0].defualt", path);
+        assert!(message.starts_with("unknown field `defualt`"), "{message}");
+
+        let (path, message) = invalid_spec("program: upload\nshel: fish");
+        assert_eq!("shel", path);
+        assert!(message.starts_with("unknown field `shel`"), "{message}");
+
+        let (path, message) = invalid_spec("commands: [{name: a, args: [{name: x, min: abc}]}]");
+        assert_eq!("commands[0].args[0].min", path);
+        assert_eq!("invalid type: string \"abc\", expected f64", message);
+
+        let (path, _) = invalid_spec("groups: [{name: g, multiple: yes}]");
+        assert_eq!("groups[0].multiple", path);
+    }
+
+    #[test]
+    fn test_load_args() -> anyhow::Result<()> {
+        let spec = SpecFormat::Yaml.load(
+            r#"
+args:
+  - "[DST]"
+  - name: ratio
+    default: 1.0
+    select: [1.0, 2, true]
+    requires: DST
+    min: 0
+"#,
+        )?;
+        assert_eq!(ArgSpec::from("[DST]"), spec.args[0]);
+        assert_eq!(
+            ArgSpec {
+                name: Some("ratio".to_owned()),
+                default: Some(DefaultValue::One("1.0".to_owned())),
+                select: Some(vec!["1.0".to_owned(), "2".to_owned(), "true".to_owned()]),
+                requires: vec!["DST".to_owned()],
+                min: Some(0.0),
+                ..Default::default()
+            },
+            spec.args[1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_errors() {
+        assert!(matches!(
+            SpecFormat::Json.load("{\"version\": "),
+            Err(Error::ParseJson(_))
+        ));
+        assert!(matches!(
+            SpecFormat::Toml.load("version = "),
+            Err(Error::ParseToml(_))
+        ));
+        assert!(matches!(SpecFormat::Yaml.load(""), Err(Error::NoDocs)));
+    }
+}
@@ -0,0 +1,99 @@
+use std::process::{Command, Output, Stdio};
+
+fn check(spec: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ramen"))
+        .arg("check")
+        .arg(spec)
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run ramen")
+}
+
+#[test]
+fn test_check() {
+    let output = check(
+        r#"
+version: "1.0.0"
+program: upload
+args:
+  - FILES...
+  - DST
+  - name: protocol
+    long: --protocol
+    default: ftp
+    select: [scp, rsync]
+  - name: verbose
+    short: -v
+    type: count
+    default: abc
+"#,
+    );
+
+    assert_eq!(Some(1), output.status.code());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("default \"ftp\" is not one of [\"scp\", \"rsync\"], (key: args[2].default[0])\n --> line 9, column 14"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("invalid default, \"abc\" is not a valid count, (key: args[3].default[0])\n  --> line 14, column 14"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("found 2 problem(s) in the spec"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_check_invalid_spec() {
+    let output =
+        check("version: \"1.0.0\"\nprogram: upload\nargs:\n  - {name: x, tpye: integer}\n");
+
+    assert_eq!(Some(1), output.status.code());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown field `tpye`"), "{stderr}");
+    assert!(stderr.contains(" --> line 4, column 15"), "{stderr}");
+}
+
+#[test]
+fn test_check_unbuildable_spec() {
+    // Specs which clap can't build are errors rather than panics.
+    for (spec, message) in [
+        (
+            "args: [-h/--host]",
+            "-h is reserved for the help, (key: args[].short)",
+        ),
+        (
+            "args: [\"[A]\", B]",
+            "optional positional argument \"A\" can't come before the required \"B\"",
+        ),
+        (
+            "args: [FILES..., SRCS...]",
+            "positional argument \"SRCS\" never gets a value after the variadic \"FILES\"",
+        ),
+        (
+            "commands: [{name: a}, {name: b, aliases: [a]}]",
+            "command name \"a\" is used more than once",
+        ),
+        (
+            "args: [-t/--threads, {name: jobs, long: --threads}]",
+            "--threads is used by both \"threads\" and \"jobs\"",
+        ),
+    ] {
+        let output = check(&format!("version: \"1.0.0\"\nprogram: upload\n{spec}\n"));
+
+        assert_eq!(Some(1), output.status.code(), "{output:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{stderr}");
+        assert!(stderr.contains(" --> line 3, column "), "{stderr}");
+    }
+}
+
+#[test]
+fn test_check_valid_spec() {
+    let output = check("version: \"1.0.0\"\nprogram: upload\nargs: [SRC, DST, -v/--verbose]\n");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty());
+}
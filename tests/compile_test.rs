@@ -0,0 +1,303 @@
+use std::process::{Command, Output, Stdio};
+
+const UPLOAD: &str = r#"
+version: "1.0.0"
+program: upload
+env_prefix: UP_
+args:
+  - SRC
+  - "[DST]"
+  - -v/--verbose
+  - name: threads
+    short: -t
+    long: --threads
+    type: integer
+    default: 8
+    min: 1
+    max: 64
+  - name: protocol
+    long: --protocol
+    select: [scp, rsync]
+  - name: verbosity
+    short: -V
+    type: count
+    max: 3
+  - name: include
+    short: -i
+    multiple: true
+"#;
+
+const DEPLOY: &str = r#"
+version: "1.0.0"
+program: deploy
+env_prefix: DEPLOY_
+output_prefix: my_
+declare: local
+args:
+  - TARGET
+  - name: region
+    short: -r
+    long: --region
+    select: [eu, us]
+    default: eu
+  - name: ratio
+    long: --ratio
+    type: float
+    min: 0.5
+    max: 2.5
+commands:
+  - name: apply
+    about: Apply the changes.
+    aliases: [up]
+    passthrough: trailing
+    args:
+      - name: force
+        long: --force
+        type: boolean
+        conflicts_with: dry
+      - name: dry
+        long: --dry
+        type: boolean
+      - name: token
+        long: --token
+        requires: user
+      - name: user
+        long: --user
+      - "[FILES...]"
+  - name: plan
+    passthrough: unknown
+    args:
+      - OUT
+      - name: level
+        short: -l
+        type: number
+  - name: run
+    args:
+      - name: fast
+        long: --fast
+        type: boolean
+      - name: slow
+        long: --slow
+        type: boolean
+    groups:
+      - name: mode
+        args: [fast, slow]
+        required: true
+"#;
+
+const TOOL: &str = r#"
+version: "1.0.0"
+program: tool
+array_style: indexed
+declare: typed
+naming: SCREAMING_SNAKE
+args:
+  - name: dry-run
+    long: --dry-run
+    type: boolean
+    env: TOOL_DRY
+  - name: verbose
+    short: -v
+    type: count
+    env: TOOL_VERBOSE
+  - name: level
+    long: --level
+    type: integer
+    env: TOOL_LEVEL
+    help: The level.
+  - name: tags
+    long: --tag
+    multiple: true
+    default: [a, b]
+    env: TOOL_TAGS
+  - name: mode
+    long: --mode
+    required: true
+    select: [fast, slow]
+    env: TOOL_MODE
+  - name: url
+    long: --url
+  - name: ports
+    short: -p
+    type: integer
+    multiple: true
+  - SRC
+  - FILES...
+groups:
+  - name: source
+    args: [url, SRC]
+    required: true
+"#;
+
+fn run(command: &mut Command, envs: &[(&str, &str)]) -> Output {
+    command
+        .envs(envs.iter().copied())
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run the command")
+}
+
+/// Assert the compiled parser prints the same script as ramen, for each of
+/// the command lines.
+fn assert_conforms(spec: &str, envs: &[(&str, &str)], cases: &[&[&str]]) {
+    let output = run(
+        Command::new(env!("CARGO_BIN_EXE_ramen")).args(["compile", spec]),
+        &[],
+    );
+    assert!(output.status.success(), "{output:?}");
+    let script = format!(
+        "{}\nramen_parse \"$@\"",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    for args in cases {
+        let native = run(
+            Command::new(env!("CARGO_BIN_EXE_ramen"))
+                .arg(spec)
+                .arg("--")
+                .args(*args),
+            envs,
+        );
+        let compiled = run(
+            Command::new("bash")
+                .args(["-c", &script, "bash"])
+                .args(*args),
+            envs,
+        );
+        assert_eq!(
+            (
+                String::from_utf8_lossy(&native.stdout),
+                native.status.code()
+            ),
+            (
+                String::from_utf8_lossy(&compiled.stdout),
+                compiled.status.code()
+            ),
+            "{args:?}, stderr: {}",
+            String::from_utf8_lossy(&compiled.stderr)
+        );
+    }
+}
+
+#[test]
+fn test_compile_values() {
+    assert_conforms(
+        UPLOAD,
+        &[],
+        &[
+            &["a"],
+            &["a", "b", "-v", "--threads=16", "--protocol", "rsync"],
+            &["-vVV", "-t4", "a", "-i", "x", "-i=y", "-iz", "it's"],
+            &["-VVVVV", "a", "--", "-b"],
+            &["a", "-t", "-3"],
+            &["a", "--threads", "0x10"],
+            &["a", "--threads", "08", "-t", "+010"],
+            &["a", "-t", "99999999999999999999"],
+            &["a", "-t", "9223372036854775808"],
+            &["a", "-t", "-9223372036854775809"],
+            &["a", "--protocol", "rsyn"],
+            &["a", "--protocol"],
+            &["a", "--thread", "3"],
+            &["a", "-x"],
+            &["a", "b", "c"],
+            &["a", "-v", "-v"],
+            &["a", "--verbose=yes"],
+            &["--threads=5", "--threads"],
+            &[],
+        ],
+    );
+}
+
+#[test]
+fn test_compile_env() {
+    assert_conforms(
+        UPLOAD,
+        &[("UP_PROTOCOL", "rsync"), ("UP_VERBOSITY", "2")],
+        &[&["a"], &["a", "--protocol", "scp", "-V"]],
+    );
+    assert_conforms(UPLOAD, &[("UP_VERBOSE", "maybe")], &[&["a"]]);
+    assert_conforms(
+        UPLOAD,
+        &[("UP_THREADS", "99")],
+        &[&["a"], &["a", "-t", "2"]],
+    );
+}
+
+#[test]
+fn test_compile_subcommands() {
+    assert_conforms(
+        DEPLOY,
+        &[],
+        &[
+            &["prod", "apply", "--force", "f1", "--", "-x", "y"],
+            &["prod", "up", "--token", "t", "--user", "me"],
+            &[
+                "prod", "-rus", "--ratio", "1.5", "plan", "out", "-l", "-2", "--extra",
+            ],
+            &["prod", "run", "--fast"],
+            &["prod", "run", "--fast", "--slow"],
+            &["prod", "run"],
+            &["prod"],
+            &["prod", "aply"],
+            &["prod", "--", "apply"],
+            &["prod", "apply", "--force", "--dry"],
+            &["prod", "apply", "--token", "t"],
+            &["prod", "--ratio", "3", "apply", "--nope"],
+            &["prod", "--ratio", "1e200000", "run", "--fast"],
+            &["prod", "--ratio", "1e400", "run", "--fast"],
+            &["prod", "--ratio", "1e99999999999999999999", "run", "--fast"],
+            &["prod", "--ratio", "1e-200000", "run", "--fast"],
+            &["prod", "--ratio", "0.0025e3", "run", "--fast"],
+            &["prod", "--slow", "run"],
+            &["--help"],
+            &["prod", "apply", "-h"],
+            &["help", "plan"],
+            &["help", "help", "apply"],
+        ],
+    );
+}
+
+#[test]
+fn test_compile_groups() {
+    assert_conforms(
+        TOOL,
+        &[],
+        &[
+            &["--mode", "fast", "src", "f1", "f2"],
+            &["--mode", "slow", "--url", "u", "-vv", "--tag", "x", "f1"],
+            &["--mode", "slow", "--url", "u", "src", "f1"],
+            &["--mode", "fst", "src", "f1"],
+            &["f1"],
+            &["--level", "x", "--dry-run=x"],
+            &["--mode", "fast", "-p", "08", "-p", "-010", "src", "f1"],
+        ],
+    );
+    assert_conforms(
+        TOOL,
+        &[
+            ("TOOL_MODE", "slow"),
+            ("TOOL_TAGS", "z"),
+            ("TOOL_VERBOSE", "2"),
+        ],
+        &[&["src", "f1"]],
+    );
+    assert_conforms(TOOL, &[("TOOL_VERBOSE", "300")], &[&["src", "f1"]]);
+}
+
+#[test]
+fn test_compile_other_shell() {
+    // The compiled parser only writes bash, so specs for the other shells
+    // are rejected rather than compiled to a parser printing bash.
+    let spec = format!("{UPLOAD}shell: sh\n");
+    let output = run(
+        Command::new(env!("CARGO_BIN_EXE_ramen")).args(["compile", &spec]),
+        &[],
+    );
+    assert_eq!(Some(1), output.status.code(), "{output:?}");
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the compiled parser is written in bash, but the spec is for sh"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,53 @@
+use std::process::{Command, Stdio};
+
+const SPEC: &str = r#"
+version: "1.0.0"
+program: upload
+args:
+  - SRC
+  - -t/--threads
+  - name: protocol
+    long: --protocol
+    select: [scp, rsync]
+"#;
+
+/// Complete the given words in bash with the generated script.
+fn complete_in_bash(words: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ramen"))
+        .args(["completions", "--shell", "bash", SPEC])
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run ramen");
+    assert!(output.status.success(), "{output:?}");
+
+    let script = format!(
+        r#"{}
+COMP_WORDS=({})
+COMP_CWORD=$(( ${{#COMP_WORDS[@]}} - 1 ))
+_upload upload "${{COMP_WORDS[COMP_CWORD]}}" "${{COMP_WORDS[COMP_CWORD-1]}}"
+printf '%s\n' "${{COMPREPLY[@]}}"
+"#,
+        String::from_utf8_lossy(&output.stdout),
+        words
+            .iter()
+            .map(|x| format!("'{x}'"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    let output = Command::new("bash")
+        .args(["-c", &script])
+        .output()
+        .expect("failed to run bash");
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_bash_completions() {
+    let options = complete_in_bash(&["upload", "--"]);
+    assert!(options.lines().any(|x| x == "--threads"), "{options}");
+    assert!(options.lines().any(|x| x == "--protocol"), "{options}");
+
+    let values = complete_in_bash(&["upload", "--protocol", ""]);
+    assert_eq!("scp\nrsync\n", values);
+}
@@ -0,0 +1,289 @@
+use ramen::parser::{ArgumentParser, Error, ParseOptions};
+use ramen::shell::{Declaration, Shell};
+use std::process::Command;
+
+const PROGRAM: &str = r#"
+version: "1.0.0"
+program: upload
+passthrough: trailing
+args:
+  - FILES...
+  - name: verbose
+    short: -v
+    type: count
+  - name: dry-run
+    long: --dry-run
+    type: boolean
+  - name: message
+    short: -m
+    long: --message
+"#;
+
+const OPTSTRING: &[&str] = &[
+    "a.txt",
+    "it's.txt",
+    "-vv",
+    "--message",
+    r#"say \"hi\"\n"#,
+    "--",
+    "--foo",
+];
+
+#[test]
+fn test_golden_bash() {
+    assert_eq!(
+        r#"FILES=('a.txt' 'it'\''s.txt')
+FILES_count=2
+verbose=2
+dry_run=false
+message='say \"hi\"\n'
+set -- '--foo'
+"#,
+        parse(Shell::Bash)
+    );
+}
+
+#[test]
+fn test_golden_zsh() {
+    assert_eq!(parse(Shell::Bash), parse(Shell::Zsh));
+}
+
+#[test]
+fn test_golden_sh() {
+    assert_eq!(
+        r#"FILES_0='a.txt'
+FILES_1='it'\''s.txt'
+FILES_count=2
+verbose=2
+dry_run=false
+message='say \"hi\"\n'
+set -- '--foo'
+"#,
+        parse(Shell::Sh)
+    );
+}
+
+#[test]
+fn test_golden_fish() {
+    assert_eq!(
+        r#"set FILES 'a.txt' 'it\'s.txt'
+set FILES_count 2
+set verbose 2
+set dry_run false
+set message 'say \\"hi\\"\\n'
+set argv '--foo'
+"#,
+        parse(Shell::Fish)
+    );
+}
+
+#[test]
+fn test_shell_key_in_spec() {
+    let spec = format!("{PROGRAM}shell: fish\n");
+    let parser = ArgumentParser::from_yaml(&spec).unwrap();
+    let output = parser.parse(OPTSTRING, &ParseOptions::default()).unwrap();
+    assert_eq!(parse(Shell::Fish), output);
+
+    // The shell given in the options takes precedence.
+    let options = ParseOptions {
+        shell: Some(Shell::Sh),
+        ..Default::default()
+    };
+    assert_eq!(parse(Shell::Sh), parser.parse(OPTSTRING, &options).unwrap());
+}
+
+#[test]
+fn test_declare_local() {
+    assert_eq!(
+        r#"local FILES=('a.txt' 'it'\''s.txt')
+local FILES_count=2
+local verbose=2
+local dry_run=false
+local message='say \"hi\"\n'
+set -- '--foo'
+"#,
+        parse_declared(Shell::Bash, Declaration::Local).unwrap()
+    );
+    assert_eq!(
+        r#"set -l FILES 'a.txt' 'it\'s.txt'
+set -l FILES_count 2
+set -l verbose 2
+set -l dry_run false
+set -l message 'say \\"hi\\"\\n'
+set argv '--foo'
+"#,
+        parse_declared(Shell::Fish, Declaration::Local).unwrap()
+    );
+}
+
+#[test]
+fn test_declare_typed() {
+    assert_eq!(
+        r#"declare -a FILES=('a.txt' 'it'\''s.txt')
+declare -i FILES_count=2
+declare -i verbose=2
+declare dry_run=false
+declare message='say \"hi\"\n'
+set -- '--foo'
+"#,
+        parse_declared(Shell::Bash, Declaration::Typed).unwrap()
+    );
+}
+
+#[test]
+fn test_declare_key_in_spec() {
+    let spec = format!("{PROGRAM}declare: export\n");
+    let parser = ArgumentParser::from_yaml(&spec).unwrap();
+    let output = parser.parse(OPTSTRING, &ParseOptions::default()).unwrap();
+    assert_eq!(
+        parse_declared(Shell::Bash, Declaration::Export).unwrap(),
+        output
+    );
+
+    let spec = format!("{PROGRAM}declare: global\n");
+    let err = ArgumentParser::from_yaml(&spec).err().unwrap();
+    assert!(matches!(
+        err.without_location(),
+        Error::InvalidSpec { path, .. } if path == "declare"
+    ));
+}
+
+#[test]
+fn test_unsupported_declaration() {
+    for (shell, declaration) in [
+        (Shell::Sh, Declaration::Typed),
+        (Shell::Fish, Declaration::Readonly),
+        (Shell::Fish, Declaration::Typed),
+    ] {
+        assert!(
+            matches!(
+                parse_declared(shell, declaration),
+                Err(Error::UnsupportedDeclaration { .. })
+            ),
+            "{shell} {declaration}"
+        );
+    }
+}
+
+#[test]
+fn test_eval_local_in_bash_function() {
+    let script = parse_declared(Shell::Bash, Declaration::Local).unwrap();
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(
+            r#"set -u; main() { eval "$1"; printf '%s|' "${FILES[@]}" "$verbose"; }; main "$1"; printf '%s' "${verbose-unset}""#,
+        )
+        .arg("bash")
+        .arg(&script)
+        .output()
+        .expect("failed to run bash");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        "a.txt|it's.txt|2|unset",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn test_eval_typed_integers_in_bash() {
+    const INTEGERS: &str = r#"
+    version: "1.0.0"
+    program: upload
+    declare: typed
+    args:
+      - name: threads
+        short: -t
+        type: integer
+      - name: level
+        short: -l
+        type: integer
+        select: ["1", "010"]
+      - name: ports
+        short: -p
+        type: integer
+        multiple: true
+    "#;
+
+    // `declare -i` reads a leading zero as octal, so the integers are written
+    // in plain decimal, and the choices of a select as strings.
+    for (threads, expected) in [("08", "8|9|010|13|"), ("010", "10|11|010|13|")] {
+        let parser = ArgumentParser::from_yaml(INTEGERS).unwrap();
+        let optstring = ["-t", threads, "-l", "010", "-p", "08", "-p", "+5"];
+        let script = parser.parse(optstring, &ParseOptions::default()).unwrap();
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(
+                r#"set -eu; eval "$1"; printf '%s|' "$threads" "$((threads + 1))" "$level" "$((ports[0] + ports[1]))""#,
+            )
+            .arg("bash")
+            .arg(&script)
+            .output()
+            .expect("failed to run bash");
+
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(expected, String::from_utf8_lossy(&output.stdout));
+    }
+}
+
+#[test]
+fn test_eval_in_dash() {
+    let script = parse(Shell::Sh);
+    let output = Command::new("dash")
+        .arg("-c")
+        .arg(
+            r#"set -eu; eval "$1"; printf '%s|' "$FILES_0" "$FILES_1" "$FILES_count" "$verbose" "$dry_run" "$message" "$@""#,
+        )
+        .arg("dash")
+        .arg(&script)
+        .output()
+        .expect("failed to run dash");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        r#"a.txt|it's.txt|2|2|false|say \"hi\"\n|--foo|"#,
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_eval_non_utf8_in_dash() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let value = OsStr::from_bytes(b"'a\xff\xfe\nb'");
+    let script = format!("value={}", Shell::Sh.quote(value));
+    let output = Command::new("dash")
+        .arg("-c")
+        .arg(r#"eval "$1"; printf '%s' "$value""#)
+        .arg("dash")
+        .arg(&script)
+        .output()
+        .expect("failed to run dash");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(value.as_bytes(), output.stdout.as_slice());
+}
+
+fn parse(shell: Shell) -> String {
+    let options = ParseOptions {
+        shell: Some(shell),
+        ..Default::default()
+    };
+    ArgumentParser::from_yaml(PROGRAM)
+        .unwrap()
+        .parse(OPTSTRING, &options)
+        .unwrap()
+}
+
+fn parse_declared(shell: Shell, declaration: Declaration) -> Result<String, Error> {
+    let options = ParseOptions {
+        shell: Some(shell),
+        declaration: Some(declaration),
+        ..Default::default()
+    };
+    ArgumentParser::from_yaml(PROGRAM)
+        .unwrap()
+        .parse(OPTSTRING, &options)
+}
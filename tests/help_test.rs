@@ -0,0 +1,79 @@
+use std::process::{Command, Output};
+
+const PROGRAM: &str = r#"
+version: "1.0.0"
+program: upload
+about: Upload files to the server.
+args:
+  - "[SRC]"
+  - name: protocol
+    short: -p
+    long: --protocol
+    select: [scp, rsync]
+"#;
+
+#[test]
+fn test_help_prints_and_exits_zero() {
+    let output = eval_in_bash(&ramen_script(PROGRAM, &["--help"]));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(Some(0), output.status.code());
+    assert!(stdout.contains("Upload files to the server."), "{stdout}");
+    assert!(stdout.contains("Usage: upload [OPTIONS] [SRC]"), "{stdout}");
+    assert!(!stdout.contains("UNREACHABLE"), "{stdout}");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_usage_error_prints_to_stderr_and_exits_two() {
+    let output = eval_in_bash(&ramen_script(PROGRAM, &["--protocol", "ftp"]));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(Some(2), output.status.code());
+    assert!(
+        stderr.contains("invalid value 'ftp' for '--protocol <protocol>'"),
+        "{stderr}"
+    );
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_bare_positional_is_required() {
+    // Bare positionals are required, only `[SRC]` is optional.
+    let spec = PROGRAM.replace("\"[SRC]\"", "SRC");
+
+    let output = eval_in_bash(&ramen_script(&spec, &["--help"]));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Usage: upload [OPTIONS] <SRC>"), "{stdout}");
+
+    let output = eval_in_bash(&ramen_script(&spec, &[]));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(Some(2), output.status.code());
+    assert!(
+        stderr.contains("the following required arguments were not provided"),
+        "{stderr}"
+    );
+}
+
+/// Run the ramen binary and return the script to eval.
+fn ramen_script(spec: &str, optstring: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ramen"))
+        .arg(spec)
+        .arg("--")
+        .args(optstring)
+        .output()
+        .expect("failed to run ramen");
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Eval the script in a bash function, as the calling script would do.
+fn eval_in_bash(script: &str) -> Output {
+    Command::new("bash")
+        .arg("-c")
+        .arg(r#"main() { eval "$1"; echo UNREACHABLE; }; main "$1""#)
+        .arg("bash")
+        .arg(script)
+        .output()
+        .expect("failed to run bash")
+}
@@ -1,4 +1,4 @@
-use ramen;
+use std::process::Command;
 
 #[test]
 fn test_parse_only_names() {
@@ -7,7 +7,7 @@ fn test_parse_only_names() {
     program: upload
     args: [SRC, DST, -v/--verbose, -t/--threads, --protocol]
     "#;
-    let optstring: Vec<String> = vec![
+    let optstring: Vec<String> = [
         "/path/to/src",
         "/path/to/dst",
         "-v",
@@ -24,11 +24,11 @@ fn test_parse_only_names() {
 
     expect_output(
         vec![
-            "SRC=/path/to/src",
-            "DST=/path/to/dst",
-            "verbose=true",
-            "threads=8",
-            "protocol=s3",
+            "SRC='/path/to/src'",
+            "DST='/path/to/dst'",
+            "verbose='true'",
+            "threads='8'",
+            "protocol='s3'",
         ],
         &output,
     )
@@ -49,7 +49,7 @@ fn test_parse_boolean_flags() {
       - -t/--threads
       - --protocol
     "#;
-    let optstring: Vec<String> = vec![
+    let optstring: Vec<String> = [
         "/path/to/src",
         "/path/to/dst",
         "-v",
@@ -65,16 +65,288 @@ fn test_parse_boolean_flags() {
 
     expect_output(
         vec![
-            "SRC=/path/to/src",
-            "DST=/path/to/dst",
+            "SRC='/path/to/src'",
+            "DST='/path/to/dst'",
             "verbose=true",
-            "threads=4",
-            "protocol=scp",
+            "threads='4'",
+            "protocol='scp'",
+        ],
+        &output,
+    )
+}
+
+#[test]
+fn test_parse_defaults() {
+    const PROGRAM: &str = r#"
+    version: "1.0.0"
+    program: upload
+    args:
+      - SRC
+      - DST
+      - name: verbose
+        short: -v
+        long: --verbose
+        type: boolean
+      - name: force
+        long: --force
+        type: boolean
+        default: true
+      - name: threads
+        short: -t
+        long: --threads
+        type: number
+        default: 8
+      - name: protocol
+        short: -p
+        long: --protocol
+        type: string
+        default: scp
+      - --comment
+    "#;
+    let optstring = ["/path/to/src", "/path/to/dst", "--protocol", "rsync"];
+    let output = ramen::parse(PROGRAM, optstring).unwrap();
+
+    expect_output(
+        vec![
+            "SRC='/path/to/src'",
+            "DST='/path/to/dst'",
+            "verbose=false",
+            "force=true",
+            "threads='8'",
+            "protocol='rsync'",
+            "comment=''",
+        ],
+        &output,
+    )
+}
+
+#[test]
+fn test_parse_multiple_values() {
+    const PROGRAM: &str = r#"
+    version: "1.0.0"
+    program: cat
+    args:
+      - FILES...
+      - name: include
+        short: -i
+        long: --include
+        multiple: true
+      - name: exclude
+        long: --exclude
+        multiple: true
+    "#;
+    let optstring = ["-i", "a b", "a.txt", "--include", "c", "it's.txt"];
+    let output = ramen::parse(PROGRAM, optstring).unwrap();
+
+    expect_output(
+        vec![
+            "FILES=('a.txt' 'it'\\''s.txt')",
+            "FILES_count=2",
+            "include=('a b' 'c')",
+            "include_count=2",
+            "exclude=()",
+            "exclude_count=0",
         ],
         &output,
     )
 }
 
+#[test]
+fn test_parse_multiple_values_indexed() {
+    const PROGRAM: &str = r#"
+    version: "1.0.0"
+    program: cat
+    array_style: indexed
+    args: ["[FILES...]"]
+    "#;
+    let output = ramen::parse(PROGRAM, ["a.txt", "b.txt"]).unwrap();
+    expect_output(
+        vec!["FILES_0='a.txt'", "FILES_1='b.txt'", "FILES_count=2"],
+        &output,
+    );
+
+    let output = ramen::parse(PROGRAM, Vec::<String>::new()).unwrap();
+    expect_output(vec!["FILES_count=0"], &output);
+}
+
+#[test]
+fn test_parse_count() {
+    const PROGRAM: &str = r#"
+    version: "1.0.0"
+    program: upload
+    args:
+      - name: verbose
+        short: -v
+        long: --verbose
+        type: count
+        max: 3
+      - name: quiet
+        short: -q
+        type: count
+    "#;
+    let output = ramen::parse(PROGRAM, ["-vv", "--verbose"]).unwrap();
+    expect_output(vec!["verbose=3", "quiet=0"], &output);
+
+    let output = ramen::parse(PROGRAM, ["-vvvvv", "-qq"]).unwrap();
+    expect_output(vec!["verbose=3", "quiet=2"], &output);
+}
+
+#[test]
+fn test_parse_env() {
+    const PROGRAM: &str = r#"
+    version: "1.0.0"
+    program: upload
+    env_prefix: RAMEN_TEST_UPLOAD_
+    args:
+      - name: threads
+        short: -t
+        long: --threads
+        type: integer
+        default: 8
+      - name: protocol
+        long: --protocol
+        env: RAMEN_TEST_PROTOCOL
+        default: scp
+      - name: dry-run
+        long: --dry-run
+        type: boolean
+    "#;
+    // The environment is given to a child process, setting it in the test
+    // process would race with the other tests.
+    let envs = [
+        ("RAMEN_TEST_UPLOAD_THREADS", "4"),
+        ("RAMEN_TEST_PROTOCOL", "rsync"),
+        ("RAMEN_TEST_UPLOAD_DRY_RUN", "true"),
+    ];
+
+    // CLI > env > default
+    let output = parse_with_env(PROGRAM, &["--threads", "2"], &envs);
+    expect_output(
+        vec!["threads='2'", "protocol='rsync'", "dry_run=true"],
+        &output,
+    );
+
+    let output = parse_with_env(PROGRAM, &[], &[envs[0], envs[2]]);
+    expect_output(
+        vec!["threads='4'", "protocol='scp'", "dry_run=true"],
+        &output,
+    );
+
+    let help = parse_with_env(PROGRAM, &["--help"], &[envs[0], envs[2]]);
+    assert!(
+        help.contains("[env: RAMEN_TEST_UPLOAD_THREADS=4]"),
+        "{help}"
+    );
+    assert!(help.contains("[env: RAMEN_TEST_PROTOCOL=]"), "{help}");
+}
+
+#[test]
+fn test_parse_subcommands() {
+    const PROGRAM: &str = r#"
+    version: "1.0.0"
+    program: deploy
+    output_prefix: ramen_
+    args: [-v/--verbose]
+    commands:
+      - name: plan
+        about: Show the changes.
+      - name: apply
+        about: Apply the changes.
+        aliases: [up]
+        args:
+          - name: force
+            long: --force
+            type: boolean
+      - name: db
+        commands:
+          - name: migrate
+            args: ["[VERSION]"]
+    "#;
+    let output = ramen::parse(PROGRAM, ["-v", "1", "apply", "--force"]).unwrap();
+    expect_output(
+        vec![
+            "ramen_verbose='1'",
+            "ramen_force=true",
+            "ramen_command='apply'",
+        ],
+        &output,
+    );
+
+    let output = ramen::parse(PROGRAM, ["up"]).unwrap();
+    expect_output(
+        vec![
+            "ramen_verbose=''",
+            "ramen_force=false",
+            "ramen_command='apply'",
+        ],
+        &output,
+    );
+
+    let output = ramen::parse(PROGRAM, ["db", "migrate", "42"]).unwrap();
+    expect_output(
+        vec![
+            "ramen_verbose=''",
+            "ramen_VERSION='42'",
+            "ramen_command='db migrate'",
+        ],
+        &output,
+    );
+
+    let err = ramen::parse(PROGRAM, ["plan", "--force"]).unwrap_err();
+    assert!(matches!(err, ramen::parser::Error::UnknownArgument { .. }));
+
+    let err = ramen::parse(PROGRAM, ["--help"]).unwrap_err().to_string();
+    assert!(
+        err.contains("apply  Apply the changes. [alias: up]"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_parse_passthrough() {
+    const TRAILING: &str = r#"
+    version: "1.0.0"
+    program: wrapper
+    passthrough: trailing
+    args: [-v/--verbose]
+    "#;
+    let output = ramen::parse(TRAILING, ["-v", "1", "--", "--foo", "a b"]).unwrap();
+    expect_output(vec!["verbose='1'", "set -- '--foo' 'a b'"], &output);
+
+    let output = ramen::parse(TRAILING, ["-v", "1"]).unwrap();
+    expect_output(vec!["verbose='1'", "set --"], &output);
+
+    let err = ramen::parse(TRAILING, ["--foo"]).unwrap_err();
+    assert!(matches!(err, ramen::parser::Error::UnknownArgument { .. }));
+
+    const UNKNOWN: &str = r#"
+    version: "1.0.0"
+    program: wrapper
+    passthrough: unknown
+    args: [-v/--verbose]
+    "#;
+    let output = ramen::parse(UNKNOWN, ["-v", "1", "--foo", "-v", "it's"]).unwrap();
+    expect_output(
+        vec!["verbose='1'", "set -- '--foo' '-v' 'it'\\''s'"],
+        &output,
+    );
+}
+
+/// Run the ramen binary with the environment, and return the output script.
+fn parse_with_env(spec: &str, optstring: &[&str], envs: &[(&str, &str)]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ramen"))
+        .arg(spec)
+        .arg("--")
+        .args(optstring)
+        .envs(envs.iter().copied())
+        .output()
+        .expect("failed to run ramen");
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_owned()
+}
+
 fn expect_output(expected_lines: Vec<&str>, got_output: &str) {
     let mut sorted_expected_lines = expected_lines.clone();
     sorted_expected_lines.sort();
@@ -0,0 +1,93 @@
+use ramen::parser::{ArgumentParser, OutputFormat, ParseOptions};
+use serde_json::{json, Value};
+
+const PROGRAM: &str = r#"
+version: "1.0.0"
+program: deploy
+args:
+  - name: verbose
+    short: -v
+    type: count
+  - name: threads
+    long: --threads
+    type: integer
+    default: 8
+  - name: ratio
+    long: --ratio
+    type: float
+  - name: region
+    long: --region
+commands:
+  - name: plan
+  - name: apply
+    passthrough: trailing
+    args:
+      - TARGETS...
+      - name: dry-run
+        long: --dry-run
+        type: boolean
+      - name: ports
+        long: --port
+        type: integer
+        multiple: true
+"#;
+
+#[test]
+fn test_json_output() {
+    let output = parse(&[
+        "-vv", "--ratio", "0.5", "apply", "web", "db", "--port", "80", "--port", "443", "--",
+        "--foo",
+    ]);
+    assert_eq!(
+        json!({
+            "args": {
+                "verbose": 2,
+                "threads": 8,
+                "ratio": 0.5,
+                "region": null,
+            },
+            "subcommand": {
+                "name": "apply",
+                "args": {
+                    "TARGETS": ["web", "db"],
+                    "dry-run": false,
+                    "ports": [80, 443],
+                },
+            },
+            "passthrough": ["--foo"],
+        }),
+        output
+    );
+}
+
+#[test]
+fn test_json_output_without_passthrough() {
+    let output = parse(&["--region", "it's", "plan"]);
+    assert_eq!(
+        json!({
+            "args": {
+                "verbose": 0,
+                "threads": 8,
+                "ratio": null,
+                "region": "it's",
+            },
+            "subcommand": {
+                "name": "plan",
+                "args": {},
+            },
+        }),
+        output
+    );
+}
+
+fn parse(optstring: &[&str]) -> Value {
+    let options = ParseOptions {
+        format: OutputFormat::Json,
+        ..Default::default()
+    };
+    let output = ArgumentParser::from_yaml(PROGRAM)
+        .unwrap()
+        .parse(optstring, &options)
+        .unwrap();
+    serde_json::from_str(&output).unwrap()
+}
@@ -0,0 +1,98 @@
+use std::ffi::OsString;
+use std::process::Command;
+
+const PROGRAM: &str = r#"
+version: "1.0.0"
+program: quoting
+args: [--value]
+"#;
+
+const ADVERSARIAL_INPUTS: &[&str] = &[
+    "",
+    " ",
+    "a b",
+    "  leading and trailing  ",
+    "tab\there",
+    "x; echo INJECTED",
+    "x && echo INJECTED",
+    "x | echo INJECTED",
+    "$(echo INJECTED)",
+    "`echo INJECTED`",
+    "${HOME}",
+    "$HOME",
+    "'",
+    "''",
+    "it's",
+    r"'\''",
+    "\"",
+    "\"double\" 'single'",
+    "\\",
+    "back\\slash\\",
+    "line1\nline2",
+    "trailing newline\n",
+    "\n\n",
+    "\r\n",
+    "*",
+    "?[a-z]*",
+    "~",
+    "!!",
+    "#comment",
+    "-rf",
+    "--value",
+    "a=b",
+    "$'\\x41'",
+    "ünïcødé 🍜",
+    "\u{7f}\u{1b}[31mred",
+];
+
+#[test]
+fn test_adversarial_values_round_trip_through_eval() {
+    for input in ADVERSARIAL_INPUTS {
+        assert_round_trip(OsString::from(input));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_values_round_trip_through_eval() {
+    use std::os::unix::ffi::OsStringExt;
+
+    let inputs: &[&[u8]] = &[
+        b"\xff",
+        b"\xff\xfe",
+        b"a\xffb",
+        b"'\xff'",
+        b"\xc3\x28",
+        b"\x80\n\x80",
+    ];
+    for input in inputs {
+        assert_round_trip(OsString::from_vec(input.to_vec()));
+    }
+}
+
+/// Parse `--value=<input>` with ramen, eval the output in bash and assert
+/// that the variable holds exactly the given input, byte by byte.
+fn assert_round_trip(input: OsString) {
+    let mut arg = OsString::from("--value=");
+    arg.push(&input);
+    let script = ramen::parse(PROGRAM, [arg]).unwrap();
+
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(r#"set -euo pipefail; eval "$1"; printf '%s' "$value""#)
+        .arg("bash")
+        .arg(&script)
+        .output()
+        .expect("failed to run bash");
+
+    assert!(
+        output.status.success(),
+        "eval failed for {input:?}, script: {script:?}, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        input.as_encoded_bytes(),
+        output.stdout.as_slice(),
+        "round trip mismatch for {input:?}, script: {script:?}"
+    );
+}
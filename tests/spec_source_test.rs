@@ -0,0 +1,118 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const SCRIPT: &str = r#"#!/usr/bin/env bash
+: <<'RAMEN'
+version: "1.0.0"
+program: upload
+args:
+  - SRC
+  - name: threads
+    long: --threads
+    default: 4
+RAMEN
+
+eval "$(ramen --from-script "$0" -- "$@")"
+printf '%s|%s|%s' "$SRC" "$threads" "$(cat)"
+"#;
+
+#[test]
+fn test_from_script() {
+    let script = write_temp_file("from_script.sh", SCRIPT);
+    let output = run_bash(&script, &["a.txt", "--threads", "8"], "piped");
+
+    assert!(output.status.success(), "{output:?}");
+    // The stdin of the script is left untouched.
+    assert_eq!("a.txt|8|piped", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_from_script_without_spec() {
+    let script = write_temp_file("no_spec.sh", "#!/usr/bin/env bash\necho hi\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_ramen"))
+        .arg("--from-script")
+        .arg(&script)
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run ramen");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no embedded spec found"), "{stderr}");
+}
+
+#[test]
+fn test_spec_file() {
+    let spec = write_temp_file(
+        "spec.yaml",
+        "version: \"1.0.0\"\nprogram: upload\nargs:\n  - SRC\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_ramen"))
+        .arg("--spec-file")
+        .arg(&spec)
+        .args(["--", "a.txt"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run ramen");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!("SRC='a.txt'\n\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_spec_file_formats() {
+    let json = write_temp_file(
+        "spec.json",
+        r#"{"version": "1.0.0", "program": "upload", "args": ["SRC"]}"#,
+    );
+    // The extension doesn't tell the format, which is detected from the content.
+    let toml = write_temp_file(
+        "spec.conf",
+        "version = \"1.0.0\"\nprogram = \"upload\"\n[[args]]\nname = \"SRC\"\n",
+    );
+    for spec in [json, toml] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ramen"))
+            .arg("--spec-file")
+            .arg(&spec)
+            .args(["--", "a.txt"])
+            .stdin(Stdio::null())
+            .output()
+            .expect("failed to run ramen");
+
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!("SRC='a.txt'\n\n", String::from_utf8_lossy(&output.stdout));
+    }
+}
+
+fn write_temp_file(name: &str, content: &str) -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+/// Run the script in bash, with ramen on the PATH.
+fn run_bash(script: &Path, args: &[&str], stdin: &str) -> Output {
+    let ramen = Path::new(env!("CARGO_BIN_EXE_ramen"));
+    let path = format!(
+        "{}:{}",
+        ramen.parent().unwrap().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let mut child = Command::new("bash")
+        .arg(script)
+        .args(args)
+        .env("PATH", path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run bash");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}